@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+
+use crate::wrap_ghost::WrapGhost;
+use crate::{GameState, GameStep};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationState {
+    Idle,
+    /// Flashes for a moment after taking a hit, then goes back to idle.
+    Hit,
+    /// Fades out and despawns the entity.
+    Dying,
+}
+
+/// How long the hit flash lasts.
+const HIT_TIME: f32 = 0.15;
+
+/// How long a destroyed enemy takes to fade out.
+const DYING_TIME: f32 = 0.3;
+
+/// Sprites are tinted by multiplying, so this washes them out to white.
+const HIT_COLOR: Color = Color::rgb(8.0, 8.0, 8.0);
+
+/// Steps a [`TextureAtlasSprite`] through the frames of its atlas.
+#[derive(Component)]
+pub struct Animation {
+    state: AnimationState,
+    frame: Timer,
+    state_timer: Timer,
+}
+
+impl Animation {
+    /// Idle animation at `fps` frames per second.
+    pub fn new(fps: f32) -> Self {
+        Animation {
+            state: AnimationState::Idle,
+            frame: Timer::from_seconds(1.0 / fps, TimerMode::Repeating),
+            state_timer: Timer::default(),
+        }
+    }
+
+    /// Restarts the hit flash, unless it is already dying.
+    pub fn hit(&mut self) {
+        if self.state != AnimationState::Dying {
+            self.state = AnimationState::Hit;
+            self.state_timer = Timer::from_seconds(HIT_TIME, TimerMode::Once);
+        }
+    }
+
+    /// Carries on from the same frame, fading out.
+    fn dying(&self) -> Self {
+        Animation {
+            state: AnimationState::Dying,
+            frame: self.frame.clone(),
+            state_timer: Timer::from_seconds(DYING_TIME, TimerMode::Once),
+        }
+    }
+
+    /// Frames play faster while hit and dying.
+    fn speed(&self) -> f32 {
+        match self.state {
+            AnimationState::Idle => 1.0,
+            AnimationState::Hit => 2.0,
+            AnimationState::Dying => 3.0,
+        }
+    }
+}
+
+/// Leaves the picture of a destroyed enemy behind to play its dying
+/// animation, so the enemy itself can go at once.
+pub fn spawn_dying(
+    commands: &mut Commands,
+    sprite: &TextureAtlasSprite,
+    atlas: &Handle<TextureAtlas>,
+    transform: &Transform,
+    animation: &Animation,
+) {
+    commands.spawn((
+        SpriteSheetBundle {
+            sprite: sprite.clone(),
+            texture_atlas: atlas.clone(),
+            transform: *transform,
+            ..default()
+        },
+        animation.dying(),
+        WrapGhost,
+    ));
+}
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Running).with_system(
+                animate
+                    .label(GameStep::Wrap)
+                    .after(GameStep::Board)
+                    .before(crate::blink_system),
+            ),
+        );
+    }
+}
+
+fn animate(
+    mut commands: Commands,
+    time: Res<Time>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut query: Query<(
+        Entity,
+        &mut Animation,
+        &mut TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+    )>,
+) {
+    for (entity, mut animation, mut sprite, texture_atlas_handle) in &mut query {
+        let speed = animation.speed();
+        animation.frame.tick(time.delta().mul_f32(speed));
+        let frames = texture_atlases
+            .get(texture_atlas_handle)
+            .map_or(1, |texture_atlas| texture_atlas.textures.len());
+        let index = (sprite.index + animation.frame.times_finished_this_tick() as usize) % frames;
+
+        let color = match animation.state {
+            AnimationState::Idle => Color::WHITE,
+            AnimationState::Hit => {
+                if animation.state_timer.tick(time.delta()).finished() {
+                    animation.state = AnimationState::Idle;
+                }
+                HIT_COLOR
+            }
+            AnimationState::Dying => {
+                let left = animation.state_timer.tick(time.delta()).percent_left();
+                if animation.state_timer.finished() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                Color::rgba(1.0, 1.0, 1.0, left)
+            }
+        };
+
+        // Only touched on a change, `sync_ghosts` copies changed sprites.
+        if sprite.index != index {
+            sprite.index = index;
+        }
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::animation::Animation;
+use crate::hull::ContactDamage;
+use crate::ron_file::load_ron;
+use crate::spawn_asteroids::Asteroid;
+use crate::spawn_doublers::{EnemyHelth, Firing, FiringPattern, TowerField};
+use crate::steering::{Obstacle, Steering};
+use crate::wrap_ghost::{WrapCollider, WrapGhost};
+
+/// One kind of enemy, see `assets/enemies.ron`.
+#[derive(Deserialize, Clone)]
+pub struct Archetype {
+    pub look: Look,
+    /// Of its collider.
+    pub radius: f32,
+    pub health: u8,
+    #[serde(default)]
+    pub mass: f32,
+    #[serde(default)]
+    pub damping: f32,
+    pub movement: Movement,
+    /// It fires one of these, picked when it spawns.
+    #[serde(default)]
+    pub firing: Vec<FiringPattern>,
+    /// Bosses switch to the attack of the last phase their health dropped
+    /// to.
+    #[serde(default)]
+    pub phases: Vec<Phase>,
+    /// How fast it shrinks the board while alive. Destroying it gives the
+    /// time back.
+    #[serde(default)]
+    pub shrink: f32,
+    #[serde(default)]
+    pub fragments: Option<Fragments>,
+    /// Chance of leaving a pickup behind when destroyed.
+    #[serde(default)]
+    pub drops: f32,
+    /// Taken off the player's hull when it runs into this, or into one of
+    /// its projectiles.
+    #[serde(default = "one")]
+    pub damage: f32,
+    #[serde(default = "one")]
+    pub shot_damage: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+/// A sprite sheet, a plain image is a sheet of one.
+#[derive(Deserialize, Clone)]
+pub struct Look {
+    pub image: String,
+    pub tile: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    pub fps: f32,
+    /// Drawn this wide instead of the size of a tile.
+    #[serde(default)]
+    pub size: Option<f32>,
+}
+
+#[derive(Deserialize, Clone)]
+pub enum Movement {
+    /// Stays put, steering enemies fly around it.
+    Still,
+    Steer(Steering),
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Phase {
+    pub health: u8,
+    pub firing: FiringPattern,
+}
+
+/// What an enemy breaks into when destroyed.
+#[derive(Deserialize, Clone)]
+pub struct Fragments {
+    pub archetype: String,
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Which archetype an enemy was spawned from.
+#[derive(Component)]
+pub struct Enemy {
+    pub archetype: String,
+}
+
+/// Every [`Archetype`] by name, with its sprite sheet.
+#[derive(Resource)]
+pub struct Archetypes {
+    kinds: BTreeMap<String, (Archetype, Handle<TextureAtlas>)>,
+}
+
+impl FromWorld for Archetypes {
+    fn from_world(world: &mut World) -> Self {
+        let archetypes = load();
+        for (name, archetype) in &archetypes {
+            if let Some(fragments) = &archetype.fragments {
+                if !archetypes.contains_key(&fragments.archetype) {
+                    warn!("{name} breaks into unknown enemy {}", fragments.archetype);
+                }
+            }
+        }
+        let images: Vec<Handle<Image>> = {
+            let asset_server = world.resource::<AssetServer>();
+            archetypes
+                .values()
+                .map(|archetype| asset_server.load(archetype.look.image.as_str()))
+                .collect()
+        };
+        let mut atlases = world.resource_mut::<Assets<TextureAtlas>>();
+        Archetypes {
+            kinds: archetypes
+                .into_iter()
+                .zip(images)
+                .map(|((name, archetype), image)| {
+                    let look = &archetype.look;
+                    let atlas = atlases.add(TextureAtlas::from_grid(
+                        image,
+                        look.tile,
+                        look.columns,
+                        look.rows,
+                        None,
+                        None,
+                    ));
+                    (name, (archetype, atlas))
+                })
+                .collect(),
+        }
+    }
+}
+
+fn load() -> BTreeMap<String, Archetype> {
+    load_ron("enemies.ron", include_str!("../assets/enemies.ron"))
+}
+
+impl Archetypes {
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        self.kinds.get(name).map(|(archetype, _)| archetype)
+    }
+
+    /// Sprite sheet of `name`, drawn as it would be in game.
+    pub fn sprite(&self, name: &str) -> Option<SpriteSheetBundle> {
+        let (archetype, atlas) = self.kinds.get(name)?;
+        Some(SpriteSheetBundle {
+            texture_atlas: atlas.clone(),
+            sprite: TextureAtlasSprite {
+                custom_size: archetype.look.size.map(Vec2::splat),
+                ..default()
+            },
+            ..default()
+        })
+    }
+
+    /// Spawns an enemy of the archetype `name`. Unknown names are logged and
+    /// spawn nothing.
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        rng: &mut impl Rng,
+        name: &str,
+        position: Vec2,
+        linvel: Vec2,
+    ) -> Option<Entity> {
+        let (Some(archetype), Some(mut sprite)) = (self.get(name), self.sprite(name)) else {
+            warn!("no enemy called {name}");
+            return None;
+        };
+        sprite.transform = Transform::from_translation(position.extend(0.0));
+        let mut enemy = commands.spawn((
+            sprite,
+            Animation::new(archetype.look.fps),
+            Asteroid,
+            Enemy {
+                archetype: name.to_string(),
+            },
+            EnemyHelth {
+                health: archetype.health,
+            },
+            Collider::ball(archetype.radius),
+            AdditionalMassProperties::Mass(archetype.mass),
+            ActiveEvents::COLLISION_EVENTS,
+            ContactDamage(archetype.damage),
+            WrapGhost,
+            WrapCollider,
+        ));
+        match &archetype.movement {
+            Movement::Still => {
+                enemy.insert(Obstacle {
+                    radius: archetype.radius,
+                });
+            }
+            Movement::Steer(steering) => {
+                enemy.insert((
+                    RigidBody::Dynamic,
+                    Velocity {
+                        angvel: 0.0,
+                        linvel,
+                    },
+                    Damping {
+                        linear_damping: archetype.damping,
+                        angular_damping: 50.5,
+                    },
+                    ExternalImpulse::default(),
+                    GravityScale(0.),
+                    LockedAxes::ROTATION_LOCKED,
+                    Steering {
+                        radius: archetype.radius,
+                        ..steering.clone()
+                    },
+                ));
+            }
+        }
+        if !archetype.firing.is_empty() {
+            let pattern = archetype.firing[rng.gen_range(0..archetype.firing.len())];
+            enemy.insert(Firing::new(pattern, archetype.shot_damage, rng));
+        }
+        if archetype.shrink > 0.0 {
+            enemy.insert(TowerField::new(archetype.shrink));
+        }
+        Some(enemy.id())
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+
+use crate::spawn_doublers::EnemyHelth;
+
+#[derive(Resource)]
+struct Poller(Timer);
+
+#[derive(Resource)]
+struct AudioDefault;
+
+#[derive(Resource)]
+struct Audio1;
+
+#[derive(Resource)]
+struct Audio2;
+
+#[derive(Resource)]
+struct Audio3;
+
+#[derive(Resource)]
+struct AudioLast;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioDefault)
+            .insert_resource(Audio1)
+            .insert_resource(Audio2)
+            .insert_resource(Audio3)
+            .insert_resource(AudioLast)
+            .insert_resource(Poller(Timer::from_seconds(1.0, TimerMode::Repeating)))
+            .add_plugin(AudioPlugin)
+            .add_audio_channel::<AudioDefault>()
+            .add_audio_channel::<Audio1>()
+            .add_audio_channel::<Audio2>()
+            .add_audio_channel::<Audio3>()
+            .add_audio_channel::<AudioLast>()
+            .add_startup_system(setup_audio)
+            .add_system(fix_volume);
+    }
+}
+
+fn setup_audio(
+    audio_def: Res<AudioChannel<AudioDefault>>,
+    audio_1: Res<AudioChannel<Audio1>>,
+    audio_2: Res<AudioChannel<Audio2>>,
+    audio_3: Res<AudioChannel<Audio3>>,
+    audio_last: Res<AudioChannel<AudioLast>>,
+    asset_server: Res<AssetServer>,
+) {
+    audio_def
+        .play(asset_server.load("astroaudio/default.wav"))
+        .looped()
+        .fade_in(AudioTween::new(
+            Duration::from_secs(5),
+            AudioEasing::OutPowi(2),
+        ))
+        .with_volume(0.3);
+    audio_1
+        .play(asset_server.load("astroaudio/1.wav"))
+        .looped()
+        .with_volume(0.0)
+        .fade_in(AudioTween::new(
+            Duration::from_secs(5),
+            AudioEasing::OutPowi(2),
+        ));
+    audio_2
+        .play(asset_server.load("astroaudio/2.wav"))
+        .looped()
+        .fade_in(AudioTween::new(
+            Duration::from_secs(5),
+            AudioEasing::OutPowi(2),
+        ))
+        .with_volume(0.0);
+    audio_3
+        .play(asset_server.load("astroaudio/3.wav"))
+        .looped()
+        .fade_in(AudioTween::new(
+            Duration::from_secs(5),
+            AudioEasing::OutPowi(2),
+        ))
+        .with_volume(0.0);
+    audio_last
+        .play(asset_server.load("astroaudio/last.wav"))
+        .looped()
+        .fade_in(AudioTween::new(
+            Duration::from_secs(5),
+            AudioEasing::OutPowi(2),
+        ))
+        .with_volume(0.0);
+}
+
+fn fix_volume(
+    audio_def: Res<AudioChannel<AudioDefault>>,
+    audio_1: Res<AudioChannel<Audio1>>,
+    audio_2: Res<AudioChannel<Audio2>>,
+    audio_3: Res<AudioChannel<Audio3>>,
+    audio_last: Res<AudioChannel<AudioLast>>,
+    enemy_helth: Query<&EnemyHelth>,
+    mut polling: ResMut<Poller>,
+    time: Res<Time>,
+) {
+    if !polling.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let mut sum_health: u16 = 0;
+    for enemy in enemy_helth.iter() {
+        sum_health += enemy.health as u16;
+    }
+    let mut tention = 0;
+    if (5..10).contains(&sum_health) {
+        tention = 1;
+    } else if (10..20).contains(&sum_health) {
+        tention = 2;
+    } else if (20..30).contains(&sum_health) {
+        tention = 3;
+    } else if (30..).contains(&sum_health) {
+        tention = 4;
+    }
+
+    audio_1
+        .set_volume(0.0)
+        .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    audio_2
+        .set_volume(0.0)
+        .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    audio_3
+        .set_volume(0.0)
+        .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    audio_last
+        .set_volume(0.0)
+        .fade_in(AudioTween::linear(Duration::from_secs(3)));
+
+    if tention == 0 {
+        audio_def
+            .set_volume(0.3)
+            .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    }
+    if tention >= 1 {
+        audio_1
+            .set_volume(0.3)
+            .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    }
+    if tention >= 2 {
+        audio_2.set_volume(0.3);
+        audio_def
+            .set_volume(0.3)
+            .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    }
+    if tention >= 3 {
+        audio_3
+            .set_volume(0.3)
+            .fade_in(AudioTween::linear(Duration::from_secs(3)));
+        audio_def
+            .set_volume(0.0)
+            .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    }
+    if tention == 4 {
+        audio_last
+            .set_volume(0.3)
+            .fade_in(AudioTween::linear(Duration::from_secs(3)));
+    }
+}
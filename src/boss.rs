@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::archetypes::{Archetypes, Enemy};
+use crate::rng::GameRng;
+use crate::spawn_doublers::{EnemyHelth, Firing};
+use crate::torus;
+use crate::waves::WaveSchedule;
+use crate::{BoardSize, GameState, GameStep, Player, TimeCounter};
+
+/// Ends the run when shot down. Its health alone is enough to push the
+/// music to full tension.
+#[derive(Component)]
+pub struct Boss;
+
+/// Whether the boss of this run has shown up yet.
+#[derive(Resource, Default)]
+pub struct BossFight {
+    pub summoned: bool,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossFight>()
+            .add_system_set(SystemSet::on_enter(GameState::Running).with_system(reset_boss_fight))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(
+                        summon_boss
+                            .label(GameStep::Board)
+                            .after(crate::spawn_doublers::time_and_adjust_board),
+                    )
+                    .with_system(
+                        change_phase
+                            .label(GameStep::Collide)
+                            .after(crate::spawn_asteroids::check_shooted),
+                    ),
+            );
+    }
+}
+
+fn reset_boss_fight(mut fight: ResMut<BossFight>) {
+    fight.summoned = false;
+}
+
+/// Brings the boss in on the far side of the board from the player once
+/// its time has come.
+pub(crate) fn summon_boss(
+    mut commands: Commands,
+    mut fight: ResMut<BossFight>,
+    time_counter: Res<TimeCounter>,
+    schedule: Res<WaveSchedule>,
+    archetypes: Res<Archetypes>,
+    board_size: Res<BoardSize>,
+    player: Query<&Transform, With<Player>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if fight.summoned || time_counter.score <= schedule.boss.at {
+        return;
+    }
+    fight.summoned = true;
+    let player = player
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    let size = board_size.size;
+    let position = torus::wrap_position(player + Vec2::splat(size / 2.0), size);
+    if let Some(boss) = archetypes.spawn(
+        &mut commands,
+        &mut rng.towers,
+        &schedule.boss.enemy,
+        position,
+        Vec2::ZERO,
+    ) {
+        commands.entity(boss).insert(Boss);
+    }
+}
+
+fn change_phase(
+    archetypes: Res<Archetypes>,
+    mut bosses: Query<(&Enemy, &EnemyHelth, &mut Firing), With<Boss>>,
+) {
+    for (enemy, health, mut firing) in bosses.iter_mut() {
+        let Some(archetype) = archetypes.get(&enemy.archetype) else {
+            continue;
+        };
+        if let Some(phase) = archetype
+            .phases
+            .iter()
+            .filter(|phase| health.health <= phase.health)
+            .min_by_key(|phase| phase.health)
+        {
+            firing.switch(phase.firing);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::hull::Hull;
+use crate::input::PlayerInput;
+use crate::ron_file::load_ron;
+use crate::wrap_ghost::{SpriteCopy, WrapGhost};
+use crate::{GameState, GameStep, Player};
+
+/// Characters in the HUD's cooldown bar.
+const BAR_LENGTH: usize = 10;
+
+/// Afterimages are drawn just behind the ship.
+const AFTERIMAGE_Z: f32 = -0.1;
+
+/// How the player's dash behaves, see `assets/dash.ron`.
+#[derive(Resource, Deserialize, Clone)]
+pub struct DashConfig {
+    pub impulse: f32,
+    pub cooldown: f32,
+    pub invulnerable: f32,
+    pub trail: f32,
+    pub afterimage_interval: f32,
+    pub afterimage_lifetime: f32,
+    pub afterimage_alpha: f32,
+}
+
+impl DashConfig {
+    pub fn load() -> Self {
+        load_ron("dash.ron", include_str!("../assets/dash.ron"))
+    }
+}
+
+/// The player's dash and how soon it can be used again.
+#[derive(Component, Default)]
+pub struct Dash {
+    /// Seconds until the next dash.
+    cooldown: f32,
+    /// Seconds the current dash still leaves a trail for.
+    trail: f32,
+    /// Seconds until the next afterimage.
+    next_afterimage: f32,
+}
+
+/// A fading picture of where the ship was during a dash.
+#[derive(Component)]
+pub struct Afterimage {
+    fade: Timer,
+}
+
+/// Shows how soon the dash is ready in the HUD.
+#[derive(Component)]
+pub struct DashDisplay;
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DashConfig::load())
+            .add_system_set(SystemSet::on_enter(GameState::Running).with_system(reset_dash))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(dash.label(GameStep::Input).after(crate::handle_input))
+                    .with_system(
+                        leave_afterimages
+                            .label(GameStep::Board)
+                            .after(crate::boss::summon_boss),
+                    )
+                    .with_system(
+                        fade_afterimages
+                            .label(GameStep::Board)
+                            .after(leave_afterimages),
+                    ),
+            )
+            .add_system(show_dash);
+    }
+}
+
+fn reset_dash(
+    mut commands: Commands,
+    mut dashes: Query<&mut Dash>,
+    afterimages: Query<Entity, With<Afterimage>>,
+) {
+    for mut dash in dashes.iter_mut() {
+        *dash = Dash::default();
+    }
+    for afterimage in afterimages.iter() {
+        commands.entity(afterimage).despawn_recursive();
+    }
+}
+
+/// Pushes the ship on top of its thrust, where it is thrusting or else
+/// where it faces.
+fn dash(
+    time: Res<Time>,
+    input: Res<PlayerInput>,
+    config: Res<DashConfig>,
+    mut player: Query<
+        (
+            &mut Dash,
+            &mut ExternalImpulse,
+            &Transform,
+            Option<&mut Hull>,
+        ),
+        With<Player>,
+    >,
+) {
+    for (mut dash, mut impulse, transform, hull) in player.iter_mut() {
+        dash.cooldown = (dash.cooldown - time.delta_seconds()).max(0.0);
+        if !input.dash || dash.cooldown > 0.0 {
+            continue;
+        }
+        let direction = input
+            .thrust_direction
+            .filter(|direction| *direction != Vec2::ZERO)
+            .unwrap_or_else(|| transform.local_y().truncate())
+            .normalize_or_zero();
+        impulse.impulse += direction * config.impulse;
+        if let Some(mut hull) = hull {
+            hull.make_invulnerable(config.invulnerable);
+        }
+        *dash = Dash {
+            cooldown: config.cooldown,
+            trail: config.trail,
+            next_afterimage: 0.0,
+        };
+    }
+}
+
+/// Drops a copy of the ship's sprite every so often while dashing. They are
+/// ghosted like the ship, so the trail also shows across the board edge.
+fn leave_afterimages(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<DashConfig>,
+    mut player: Query<(&mut Dash, &Transform, &Sprite, &Handle<Image>), With<Player>>,
+) {
+    for (mut dash, transform, sprite, texture) in player.iter_mut() {
+        if dash.trail <= 0.0 {
+            continue;
+        }
+        dash.trail -= time.delta_seconds();
+        dash.next_afterimage -= time.delta_seconds();
+        if dash.next_afterimage > 0.0 {
+            continue;
+        }
+        dash.next_afterimage += config.afterimage_interval;
+
+        let mut color = sprite.color;
+        color.set_a(config.afterimage_alpha);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    ..sprite.clone()
+                },
+                texture: texture.clone(),
+                transform: Transform {
+                    translation: transform.translation.truncate().extend(AFTERIMAGE_Z),
+                    ..*transform
+                },
+                ..default()
+            },
+            Afterimage {
+                fade: Timer::from_seconds(config.afterimage_lifetime, TimerMode::Once),
+            },
+            SpriteCopy,
+            WrapGhost,
+        ));
+    }
+}
+
+fn fade_afterimages(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<DashConfig>,
+    mut afterimages: Query<(Entity, &mut Afterimage, &mut Sprite)>,
+) {
+    for (entity, mut afterimage, mut sprite) in afterimages.iter_mut() {
+        if afterimage.fade.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        sprite
+            .color
+            .set_a(config.afterimage_alpha * afterimage.fade.percent_left());
+    }
+}
+
+fn show_dash(
+    config: Res<DashConfig>,
+    dashes: Query<&Dash, With<Player>>,
+    mut display: Query<&mut Text, With<DashDisplay>>,
+) {
+    let Ok(dash) = dashes.get_single() else {
+        return;
+    };
+    let value = if dash.cooldown <= 0.0 {
+        "Dash:  ready".to_string()
+    } else {
+        let charged = ((1.0 - dash.cooldown / config.cooldown) * BAR_LENGTH as f32) as usize;
+        format!(
+            "Dash:  [{}{}]",
+            "#".repeat(charged),
+            "-".repeat(BAR_LENGTH - charged.min(BAR_LENGTH))
+        )
+    };
+    for mut text in display.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::spawn_asteroids::{Asteroid, EnemyDestroyed};
+use crate::spawn_doublers::Projectile;
+use crate::torus;
+use crate::waves::WaveSchedule;
+use crate::wrap_ghost::SpriteCopy;
+use crate::{BoardSize, GameState, GameStep, Player, TimeCounter, FULL_BOARD};
+
+/// Seconds the kill and near miss rates average over.
+const RATE_WINDOW: f32 = 10.0;
+
+/// Seconds until a hit stops adding pressure.
+const CALM_TIME: f32 = 15.0;
+
+/// Pacing of the [`Director`], see `assets/waves.ron`.
+#[derive(Deserialize, Clone)]
+pub struct DirectorConfig {
+    /// Intensity to keep the player at, from the start of the run to when
+    /// the boss comes.
+    pub target: Target,
+    /// Bounds of [`Director::difficulty`].
+    pub min: f32,
+    pub max: f32,
+    /// Difficulty change per second for each unit the intensity is off.
+    pub response: f32,
+    /// Enemies passing closer than this are near misses.
+    pub near_miss_radius: f32,
+    /// Near misses per second that count as full pressure.
+    pub near_misses: f32,
+    /// Kills per second of a player in full control.
+    pub kills: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Target {
+    pub start: f32,
+    pub end: f32,
+}
+
+/// Watches how the player is doing and makes the game harder or easier to
+/// keep the intensity on the configured curve, whatever their skill.
+#[derive(Resource)]
+pub struct Director {
+    /// Spawn intervals are divided by this, and harder enemies join in as
+    /// it rises.
+    pub difficulty: f32,
+    /// How hard the player has it right now, from 0 to 1.
+    pub intensity: f32,
+    kill_rate: f32,
+    near_miss_rate: f32,
+    since_hit: f32,
+    /// Enemies within the near miss radius last frame.
+    near: Vec<Entity>,
+}
+
+impl Default for Director {
+    fn default() -> Self {
+        Director {
+            difficulty: 1.0,
+            intensity: 0.0,
+            kill_rate: 0.0,
+            near_miss_rate: 0.0,
+            since_hit: CALM_TIME,
+            near: vec![],
+        }
+    }
+}
+
+impl Director {
+    /// How much faster enemies steer and fire, half as steep as the
+    /// difficulty.
+    pub fn aggression(&self) -> f32 {
+        1.0 + (self.difficulty - 1.0) * 0.5
+    }
+
+    pub fn player_hit(&mut self) {
+        self.since_hit = 0.0;
+    }
+}
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Director>()
+            .add_system_set(SystemSet::on_enter(GameState::Running).with_system(reset_director))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running).with_system(
+                    direct
+                        .label(GameStep::Board)
+                        .after(crate::spawn_doublers::time_and_adjust_board),
+                ),
+            );
+    }
+}
+
+fn reset_director(mut director: ResMut<Director>) {
+    *director = Director::default();
+}
+
+fn direct(
+    mut director: ResMut<Director>,
+    mut destroyed: EventReader<EnemyDestroyed>,
+    schedule: Res<WaveSchedule>,
+    time: Res<Time>,
+    time_counter: Res<TimeCounter>,
+    board_size: Res<BoardSize>,
+    player: Query<&Transform, With<Player>>,
+    movers: Query<
+        (Entity, &Transform),
+        (
+            Or<(With<Asteroid>, With<Projectile>)>,
+            With<Velocity>,
+            Without<SpriteCopy>,
+        ),
+    >,
+) {
+    let config = &schedule.director;
+    let delta = time.delta_seconds();
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let player = player.translation.truncate();
+
+    let near: Vec<Entity> = movers
+        .iter()
+        .filter(|(_, transform)| {
+            torus::wrapped_distance(transform.translation.truncate(), player, board_size.size)
+                < config.near_miss_radius
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+    // Gone out of range, not destroyed or dropped.
+    let near_misses = director
+        .near
+        .iter()
+        .filter(|entity| !near.contains(entity) && movers.contains(**entity))
+        .count();
+    director.near = near;
+
+    let decay = 1.0 - (delta / RATE_WINDOW).min(1.0);
+    director.kill_rate = director.kill_rate * decay + destroyed.iter().count() as f32 / RATE_WINDOW;
+    director.near_miss_rate = director.near_miss_rate * decay + near_misses as f32 / RATE_WINDOW;
+    director.since_hit += delta;
+
+    let pressure = 0.4 * (director.near_miss_rate / config.near_misses).min(1.0)
+        + 0.3 * (1.0 - board_size.size / FULL_BOARD).clamp(0.0, 1.0)
+        + 0.3 * (1.0 - director.since_hit / CALM_TIME).max(0.0);
+    let control = (director.kill_rate / config.kills).min(1.0);
+    director.intensity = pressure * (1.0 - 0.5 * control);
+
+    let progress = (time_counter.score / schedule.boss.at).clamp(0.0, 1.0);
+    let target = config.target.start + (config.target.end - config.target.start) * progress;
+    director.difficulty = (director.difficulty
+        + config.response * (target - director.intensity) * delta)
+        .clamp(config.min, config.max);
+}
@@ -0,0 +1,190 @@
+use bevy::{prelude::*, sprite::Anchor};
+use bevy_rapier2d::prelude::*;
+
+use crate::animation::Animation;
+use crate::archetypes::{Archetypes, Enemy};
+use crate::spawn_asteroids::EnemyHit;
+use crate::spawn_doublers::EnemyHelth;
+use crate::wrap_ghost::{SpriteCopy, WrapGhost};
+use crate::{GameState, GameStep};
+
+/// Share of a shot's velocity it passes on to what it hits.
+const KNOCKBACK: f32 = 0.05;
+
+/// Height of a health bar, and the gap between it and its enemy.
+const BAR_HEIGHT: f32 = 4.0;
+const BAR_GAP: f32 = 8.0;
+
+const BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const BAR_FILL: Color = Color::rgb(0.9, 0.2, 0.2);
+
+/// Floats above an enemy once it has been hit. The bar is an entity of its
+/// own rather than a child, so it gets ghosts across the seams like the
+/// enemy does.
+#[derive(Component)]
+pub struct HealthBar {
+    enemy: Entity,
+    /// From the enemy's centre.
+    offset: f32,
+}
+
+/// The part of a [`HealthBar`] that shrinks with the health left.
+#[derive(Component)]
+struct HealthBarFill {
+    max: u8,
+    width: f32,
+}
+
+pub struct HitFeedbackPlugin;
+
+impl Plugin for HitFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Running)
+                .with_system(
+                    flash_and_knock_back
+                        .label(GameStep::Collide)
+                        .after(crate::spawn_asteroids::check_shooted),
+                )
+                .with_system(
+                    show_health_bars
+                        .label(GameStep::Collide)
+                        .after(crate::spawn_asteroids::check_shooted),
+                ),
+        )
+        .add_system(
+            follow_health_bars
+                .label(GameStep::Wrap)
+                .after(crate::blink_system)
+                .before(crate::wrap_ghost::spawn_seam_colliders),
+        );
+    }
+}
+
+/// Hit enemies that survive flash, and the ones that can move are pushed
+/// along the shot.
+fn flash_and_knock_back(
+    mut hits: EventReader<EnemyHit>,
+    mut enemies: Query<(
+        &EnemyHelth,
+        Option<&mut Animation>,
+        Option<&mut ExternalImpulse>,
+    )>,
+) {
+    for hit in hits.iter() {
+        let Ok((health, animation, impulse)) = enemies.get_mut(hit.enemy) else {
+            continue;
+        };
+        if health.health == 0 {
+            continue;
+        }
+        if let Some(mut animation) = animation {
+            animation.hit();
+        }
+        if let Some(mut impulse) = impulse {
+            impulse.impulse += hit.velocity * KNOCKBACK;
+        }
+    }
+}
+
+pub(crate) fn show_health_bars(
+    mut commands: Commands,
+    mut hits: EventReader<EnemyHit>,
+    enemies: Query<(&Enemy, &EnemyHelth, &Transform)>,
+    bars: Query<&HealthBar>,
+    archetypes: Res<Archetypes>,
+) {
+    let mut shown: Vec<Entity> = bars.iter().map(|bar| bar.enemy).collect();
+    for hit in hits.iter() {
+        if shown.contains(&hit.enemy) {
+            continue;
+        }
+        let Ok((enemy, health, transform)) = enemies.get(hit.enemy) else {
+            continue;
+        };
+        let Some(archetype) = archetypes.get(&enemy.archetype) else {
+            continue;
+        };
+        if health.health == 0 {
+            continue;
+        }
+        shown.push(hit.enemy);
+
+        let width = archetype.radius * 2.0;
+        let offset = archetype.radius + BAR_GAP;
+        let translation = bar_translation(transform, offset);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BAR_BACKGROUND,
+                    custom_size: Some(Vec2::new(width, BAR_HEIGHT)),
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            HealthBar {
+                enemy: hit.enemy,
+                offset,
+            },
+            SpriteCopy,
+            WrapGhost,
+        ));
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BAR_FILL,
+                    custom_size: Some(Vec2::new(width, BAR_HEIGHT)),
+                    anchor: Anchor::CenterLeft,
+                    ..default()
+                },
+                transform: Transform::from_translation(translation - Vec3::X * width / 2.0),
+                ..default()
+            },
+            HealthBar {
+                enemy: hit.enemy,
+                offset,
+            },
+            HealthBarFill {
+                max: archetype.health,
+                width,
+            },
+            SpriteCopy,
+            WrapGhost,
+        ));
+    }
+}
+
+/// Keeps the bars over their enemies, and takes them down with them.
+fn follow_health_bars(
+    mut commands: Commands,
+    mut bars: Query<(
+        Entity,
+        &HealthBar,
+        &mut Transform,
+        Option<(&HealthBarFill, &mut Sprite)>,
+    )>,
+    enemies: Query<(&Transform, &EnemyHelth), Without<HealthBar>>,
+) {
+    for (entity, bar, mut transform, fill) in bars.iter_mut() {
+        let Ok((enemy_transform, health)) = enemies.get(bar.enemy) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        transform.translation = bar_translation(enemy_transform, bar.offset);
+        if let Some((fill, mut sprite)) = fill {
+            let left = (health.health as f32 / fill.max.max(1) as f32).min(1.0);
+            transform.translation.x -= fill.width / 2.0;
+            let size = Some(Vec2::new(fill.width * left, BAR_HEIGHT));
+            // Only on a change, so the ghosts aren't copied over every frame.
+            if sprite.custom_size != size {
+                sprite.custom_size = size;
+            }
+        }
+    }
+}
+
+/// Drawn over the enemies.
+fn bar_translation(enemy: &Transform, offset: f32) -> Vec3 {
+    enemy.translation.truncate().extend(1.0) + Vec3::Y * offset
+}
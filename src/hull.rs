@@ -0,0 +1,276 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::director::Director;
+use crate::pickups::PowerUps;
+use crate::rng::GameRng;
+use crate::ron_file::load_ron;
+use crate::spawn_asteroids::Asteroid;
+use crate::spawn_doublers::Projectile;
+use crate::torus;
+use crate::wrap_ghost::{owner_collisions, SeamCollider, SpriteCopy};
+use crate::{BoardSize, GameState, GameStep, Player};
+
+/// Spots tried for a respawn, the one furthest from any enemy wins.
+const RESPAWN_CANDIDATES: usize = 16;
+
+/// How much the player's ship takes, see `assets/hull.ron`.
+#[derive(Resource, Deserialize, Clone)]
+pub struct HullConfig {
+    pub hull: f32,
+    pub shield: f32,
+    pub shield_delay: f32,
+    pub shield_regen: f32,
+    pub invulnerable: f32,
+    pub knockback: f32,
+    pub lives: Option<u32>,
+    pub respawn_invulnerable: f32,
+}
+
+impl HullConfig {
+    pub fn load() -> Self {
+        load_ron("hull.ron", include_str!("../assets/hull.ron"))
+    }
+}
+
+/// How much damage the player takes from touching this.
+#[derive(Component)]
+pub struct ContactDamage(pub f32);
+
+/// What the player's ship has left.
+#[derive(Component)]
+pub struct Hull {
+    pub hull: f32,
+    pub shield: f32,
+    /// Spare ships, `None` if the first one is the only one.
+    pub lives: Option<u32>,
+    /// Until the shield starts coming back.
+    shield_delay: Timer,
+    invulnerable: Timer,
+}
+
+impl Hull {
+    pub fn new(config: &HullConfig) -> Self {
+        Hull {
+            hull: config.hull,
+            shield: config.shield,
+            lives: config.lives,
+            shield_delay: Timer::from_seconds(config.shield_delay, TimerMode::Once),
+            invulnerable: Timer::from_seconds(0.0, TimerMode::Once),
+        }
+    }
+
+    /// Seconds it can't be hurt for.
+    pub fn invulnerable(&self) -> f32 {
+        self.invulnerable.remaining_secs()
+    }
+
+    /// Can't be hurt for `seconds`, or for as long as it already couldn't.
+    pub fn make_invulnerable(&mut self, seconds: f32) {
+        if seconds > self.invulnerable() {
+            self.invulnerable = Timer::from_seconds(seconds, TimerMode::Once);
+        }
+    }
+
+    /// Takes `damage` off the shield first, then the hull.
+    fn take(&mut self, damage: f32, config: &HullConfig) {
+        let to_shield = damage.min(self.shield);
+        self.shield -= to_shield;
+        self.hull = (self.hull - (damage - to_shield)).max(0.0);
+        self.shield_delay.reset();
+        self.make_invulnerable(config.invulnerable);
+    }
+}
+
+/// Shows the ship's hull, shield and lives in the HUD.
+#[derive(Component)]
+pub struct HullDisplay;
+
+pub struct HullPlugin;
+
+impl Plugin for HullPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HullConfig::load())
+            .add_system_set(SystemSet::on_enter(GameState::Running).with_system(repair_hull))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(
+                        damage_player
+                            .label(GameStep::Collide)
+                            .after(crate::pickups::collect_pickups),
+                    )
+                    .with_system(
+                        regenerate_shield
+                            .label(GameStep::Board)
+                            .after(GameStep::Collide),
+                    ),
+            )
+            .add_system(show_hull);
+    }
+}
+
+fn repair_hull(config: Res<HullConfig>, mut hulls: Query<&mut Hull>) {
+    for mut hull in hulls.iter_mut() {
+        *hull = Hull::new(&config);
+    }
+}
+
+/// Hurts the player for every enemy and projectile it runs into, unless it
+/// was hit a moment ago. Out of hull it comes back somewhere quiet while it
+/// has lives left, and the run ends when it doesn't.
+fn damage_player(
+    mut collisions: EventReader<CollisionEvent>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Hull,
+            &mut PowerUps,
+            &mut Transform,
+            &mut Velocity,
+            &mut ExternalImpulse,
+        ),
+        With<Player>,
+    >,
+    sources: Query<
+        (&Transform, Option<&ContactDamage>),
+        (Or<(With<Asteroid>, With<Projectile>)>, Without<Player>),
+    >,
+    enemies: Query<&Transform, (With<Asteroid>, Without<SpriteCopy>, Without<Player>)>,
+    seams: Query<(), With<SeamCollider>>,
+    config: Res<HullConfig>,
+    board_size: Res<BoardSize>,
+    mut director: ResMut<Director>,
+    mut game_state: ResMut<State<GameState>>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok((entity, mut hull, mut power_ups, mut transform, mut velocity, mut impulse)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    for (a, b) in owner_collisions(&mut collisions, &seams) {
+        let other = match (a == entity, b == entity) {
+            (true, _) => b,
+            (_, true) => a,
+            _ => continue,
+        };
+        let Ok((source, damage)) = sources.get(other) else {
+            continue;
+        };
+        if hull.invulnerable() > 0.0 || power_ups.absorb_hit() {
+            continue;
+        }
+
+        hull.take(damage.map_or(1.0, |damage| damage.0), &config);
+        director.player_hit();
+        let away = -torus::wrapped_delta(
+            transform.translation.truncate(),
+            source.translation.truncate(),
+            board_size.size,
+        );
+        impulse.impulse += away.normalize_or_zero() * config.knockback;
+
+        if hull.hull > 0.0 {
+            continue;
+        }
+        match hull.lives {
+            Some(lives) if lives > 0 => {
+                let enemies: Vec<Vec2> = enemies
+                    .iter()
+                    .map(|enemy| enemy.translation.truncate())
+                    .collect();
+                let position = torus::furthest_spot(
+                    &enemies,
+                    RESPAWN_CANDIDATES,
+                    board_size.size,
+                    &mut rng.respawns,
+                );
+                transform.translation = position.extend(transform.translation.z);
+                *velocity = Velocity::zero();
+                impulse.impulse = Vec2::ZERO;
+                *hull = Hull {
+                    lives: Some(lives - 1),
+                    ..Hull::new(&config)
+                };
+                hull.make_invulnerable(config.respawn_invulnerable);
+            }
+            _ => {
+                let _ = game_state.set(GameState::Died);
+            }
+        }
+        break;
+    }
+}
+
+fn regenerate_shield(time: Res<Time>, config: Res<HullConfig>, mut hulls: Query<&mut Hull>) {
+    for mut hull in hulls.iter_mut() {
+        hull.invulnerable.tick(time.delta());
+        if hull.shield_delay.tick(time.delta()).finished() {
+            hull.shield =
+                (hull.shield + config.shield_regen * time.delta_seconds()).min(config.shield);
+        }
+    }
+}
+
+fn show_hull(
+    hulls: Query<&Hull, With<Player>>,
+    config: Res<HullConfig>,
+    mut display: Query<&mut Text, With<HullDisplay>>,
+) {
+    let Ok(hull) = hulls.get_single() else {
+        return;
+    };
+    let lives = hull
+        .lives
+        .map_or(String::new(), |lives| format!("  Lives: {lives}"));
+    let value = format!(
+        "Hull:  {:.0}/{:.0}  Shield: {:.0}/{:.0}{lives}",
+        hull.hull.ceil(),
+        config.hull,
+        hull.shield.floor(),
+        config.shield
+    );
+    for mut text in display.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> HullConfig {
+        HullConfig {
+            hull: 5.0,
+            shield: 3.0,
+            shield_delay: 3.0,
+            shield_regen: 1.0,
+            invulnerable: 1.0,
+            knockback: 300.0,
+            lives: Some(3),
+            respawn_invulnerable: 3.0,
+        }
+    }
+
+    #[test]
+    fn take_drains_shield_first() {
+        let config = config();
+        let mut hull = Hull::new(&config);
+        hull.take(2.0, &config);
+        assert_eq!((hull.shield, hull.hull), (1.0, 5.0));
+        hull.take(2.0, &config);
+        assert_eq!((hull.shield, hull.hull), (0.0, 4.0));
+    }
+
+    #[test]
+    fn take_stops_at_zero_and_makes_invulnerable() {
+        let config = config();
+        let mut hull = Hull::new(&config);
+        hull.take(20.0, &config);
+        assert_eq!((hull.shield, hull.hull), (0.0, 0.0));
+        assert_eq!(hull.invulnerable(), config.invulnerable);
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::PathBuf,
+};
+
+use bevy::{
+    input::{mouse::MouseWheel, InputSystem},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+/// Something the player can do. Devices only reach gameplay through the
+/// [`Bindings`] of these.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Thrust,
+    Fire,
+    Aim,
+    Restart,
+    Pause,
+    ShrinkBoard,
+    GrowBoard,
+    NextWeapon,
+    PreviousWeapon,
+    /// Switches to the weapon of this number, counting from 0.
+    SelectWeapon(usize),
+    Dash,
+}
+
+/// A physical input that can drive an [`Action`]. Any of them switches the
+/// action on; the cursor and the sticks also give it a direction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(GamepadButtonType),
+    Cursor,
+    LeftStick,
+    RightStick,
+    /// The mouse wheel, turned either way for a frame.
+    ScrollUp,
+    ScrollDown,
+}
+
+/// Which inputs drive which action, kept in `controls.ron` in the user's
+/// config folder.
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct Bindings(pub BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        Bindings(
+            BTreeMap::from([
+                (Action::Thrust, vec![Mouse(MouseButton::Left), LeftStick]),
+                (
+                    Action::Fire,
+                    vec![
+                        Mouse(MouseButton::Right),
+                        Pad(GamepadButtonType::RightTrigger2),
+                        Pad(GamepadButtonType::RightTrigger),
+                    ],
+                ),
+                // A deflected stick takes over from the cursor.
+                (Action::Aim, vec![RightStick, Cursor]),
+                (
+                    Action::Restart,
+                    vec![Key(KeyCode::R), Pad(GamepadButtonType::Start)],
+                ),
+                (
+                    Action::Pause,
+                    vec![Key(KeyCode::Escape), Pad(GamepadButtonType::Select)],
+                ),
+                (
+                    Action::ShrinkBoard,
+                    vec![Key(KeyCode::Q), Pad(GamepadButtonType::DPadDown)],
+                ),
+                (
+                    Action::GrowBoard,
+                    vec![Key(KeyCode::E), Pad(GamepadButtonType::DPadUp)],
+                ),
+                (
+                    Action::NextWeapon,
+                    vec![ScrollUp, Pad(GamepadButtonType::DPadRight)],
+                ),
+                (
+                    Action::PreviousWeapon,
+                    vec![ScrollDown, Pad(GamepadButtonType::DPadLeft)],
+                ),
+                (
+                    Action::Dash,
+                    vec![Key(KeyCode::Space), Pad(GamepadButtonType::South)],
+                ),
+            ])
+            .into_iter()
+            .chain(
+                [
+                    KeyCode::Key1,
+                    KeyCode::Key2,
+                    KeyCode::Key3,
+                    KeyCode::Key4,
+                    KeyCode::Key5,
+                    KeyCode::Key6,
+                    KeyCode::Key7,
+                    KeyCode::Key8,
+                    KeyCode::Key9,
+                ]
+                .into_iter()
+                .enumerate()
+                .map(|(number, key)| (Action::SelectWeapon(number), vec![Key(key)])),
+            )
+            .collect(),
+        )
+    }
+}
+
+impl Bindings {
+    pub fn path() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?),
+            None if cfg!(target_os = "macos") => {
+                PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support")
+            }
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("bevy-astro").join("controls.ron"))
+    }
+
+    /// The user's bindings. The first time around the defaults are written
+    /// out, so there is a file to edit.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => match ron::from_str::<Self>(&text) {
+                Ok(mut bindings) => {
+                    // Actions added since the file was written.
+                    for (action, defaults) in Self::default().0 {
+                        bindings.0.entry(action).or_insert(defaults);
+                    }
+                    bindings
+                }
+                Err(e) => {
+                    warn!("using the default controls, {}: {e}", path.display());
+                    Self::default()
+                }
+            },
+            Err(_) => {
+                let bindings = Self::default();
+                if let Err(e) = bindings.save() {
+                    warn!("could not save controls: {e}");
+                }
+                bindings
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("no config folder")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+        let text = ron::ser::to_string_pretty(self, default()).map_err(|e| e.to_string())?;
+        fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Any binding of `action` switches it on, and the first one with a
+    /// direction gives it. `None` if it is off.
+    fn read(&self, action: Action, devices: &Devices) -> Option<ActionState> {
+        self.0
+            .get(&action)
+            .into_iter()
+            .flatten()
+            .filter_map(|binding| devices.read(binding))
+            .reduce(|a, b| ActionState {
+                just_pressed: a.just_pressed || b.just_pressed,
+                direction: a.direction.or(b.direction),
+            })
+    }
+}
+
+/// State of each [`Action`] this frame. Gameplay reads this instead of the
+/// devices, so the game can also be driven by a script.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct PlayerInput {
+    /// Angle between the aim direction and the x axis, as `handle_input`
+    /// expects it. `None` keeps the current rotation.
+    pub aim: Option<f32>,
+    pub thrust: bool,
+    /// Thrust this way instead of where the ship faces, scaled by its length
+    /// up to 1.
+    pub thrust_direction: Option<Vec2>,
+    pub fire: bool,
+    pub shrink_board: bool,
+    pub grow_board: bool,
+    pub restart: bool,
+    pub pause: bool,
+    /// Number of the weapon to switch to, counting from 0.
+    #[serde(default)]
+    pub select_weapon: Option<usize>,
+    /// Weapons to step through, backwards if negative.
+    #[serde(default)]
+    pub cycle_weapon: i32,
+    #[serde(default)]
+    pub dash: bool,
+}
+
+/// Frames of input to play back, one per update. Once it runs dry the
+/// player lets go of everything.
+#[derive(Resource, Default)]
+pub struct InputScript {
+    pub frames: VecDeque<PlayerInput>,
+}
+
+impl InputScript {
+    pub fn new(frames: impl IntoIterator<Item = PlayerInput>) -> Self {
+        InputScript {
+            frames: frames.into_iter().collect(),
+        }
+    }
+}
+
+pub struct DeviceInputPlugin;
+
+impl Plugin for DeviceInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load())
+            .add_system_to_stage(CoreStage::PreUpdate, read_device_input.after(InputSystem));
+    }
+}
+
+/// Stick deflection below which the stick counts as let go.
+const STICK_DEADZONE: f32 = 0.25;
+
+struct ActionState {
+    just_pressed: bool,
+    direction: Option<Vec2>,
+}
+
+struct Devices<'a> {
+    keys: &'a Input<KeyCode>,
+    mouse_buttons: &'a Input<MouseButton>,
+    pad_buttons: &'a Input<GamepadButton>,
+    pad_axes: &'a Axis<GamepadAxis>,
+    gamepads: &'a Gamepads,
+    cursor: Option<Vec2>,
+    /// How far the mouse wheel turned this frame, up is positive.
+    scroll: f32,
+}
+
+impl Devices<'_> {
+    fn read(&self, binding: &Binding) -> Option<ActionState> {
+        let button = |pressed: bool, just_pressed: bool| {
+            pressed.then_some(ActionState {
+                just_pressed,
+                direction: None,
+            })
+        };
+        let direction = |direction: Option<Vec2>| {
+            direction.map(|direction| ActionState {
+                just_pressed: false,
+                direction: Some(direction),
+            })
+        };
+        match binding {
+            Binding::Key(key) => button(self.keys.pressed(*key), self.keys.just_pressed(*key)),
+            Binding::Mouse(mouse_button) => button(
+                self.mouse_buttons.pressed(*mouse_button),
+                self.mouse_buttons.just_pressed(*mouse_button),
+            ),
+            Binding::Pad(button_type) => self.gamepads.iter().find_map(|gamepad| {
+                let pad_button = GamepadButton::new(gamepad, *button_type);
+                button(
+                    self.pad_buttons.pressed(pad_button),
+                    self.pad_buttons.just_pressed(pad_button),
+                )
+            }),
+            Binding::Cursor => direction(self.cursor),
+            Binding::LeftStick => {
+                direction(self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY))
+            }
+            Binding::RightStick => {
+                direction(self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY))
+            }
+            Binding::ScrollUp => button(self.scroll > 0., self.scroll > 0.),
+            Binding::ScrollDown => button(self.scroll < 0., self.scroll < 0.),
+        }
+    }
+
+    fn stick(&self, x: GamepadAxisType, y: GamepadAxisType) -> Option<Vec2> {
+        self.gamepads.iter().find_map(|gamepad| {
+            let axis = |axis_type| {
+                self.pad_axes
+                    .get(GamepadAxis::new(gamepad, axis_type))
+                    .unwrap_or(0.)
+            };
+            let stick = Vec2::new(axis(x), axis(y));
+            (stick.length() > STICK_DEADZONE).then(|| stick.clamp_length_max(1.))
+        })
+    }
+}
+
+fn read_device_input(
+    mut input: ResMut<PlayerInput>,
+    bindings: Res<Bindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    pad_axes: Res<Axis<GamepadAxis>>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    let devices = Devices {
+        keys: &keys,
+        mouse_buttons: &mouse_buttons,
+        pad_buttons: &pad_buttons,
+        pad_axes: &pad_axes,
+        gamepads: &gamepads,
+        cursor: windows.get_primary().and_then(|window| {
+            window.physical_cursor_position().map(|targ| {
+                targ.as_vec2()
+                    - Vec2 {
+                        x: window.width() / 2.,
+                        y: window.height() / 2.,
+                    }
+            })
+        }),
+        scroll: wheel.iter().map(|event| event.y).sum(),
+    };
+    let held = |action| bindings.read(action, &devices);
+    let just_pressed = |action| held(action).is_some_and(|state| state.just_pressed);
+
+    input.aim = held(Action::Aim)
+        .and_then(|state| state.direction)
+        .map(|direction| direction.angle_between(Vec2::X));
+    let thrust = held(Action::Thrust);
+    input.thrust = thrust.is_some();
+    input.thrust_direction = thrust.and_then(|state| state.direction);
+    input.fire = held(Action::Fire).is_some();
+    input.shrink_board = just_pressed(Action::ShrinkBoard);
+    input.grow_board = just_pressed(Action::GrowBoard);
+    input.restart = just_pressed(Action::Restart);
+    input.pause = just_pressed(Action::Pause);
+    input.select_weapon = bindings.0.keys().find_map(|&action| match action {
+        Action::SelectWeapon(number) if just_pressed(action) => Some(number),
+        _ => None,
+    });
+    input.cycle_weapon =
+        just_pressed(Action::NextWeapon) as i32 - just_pressed(Action::PreviousWeapon) as i32;
+    input.dash = just_pressed(Action::Dash);
+}
+
+pub fn play_input_script(mut input: ResMut<PlayerInput>, mut script: ResMut<InputScript>) {
+    *input = script.frames.pop_front().unwrap_or_default();
+}
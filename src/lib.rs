@@ -0,0 +1,694 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy, utils::Instant};
+
+use bevy_rapier2d::prelude::*;
+
+pub mod animation;
+pub mod archetypes;
+pub mod audio;
+pub mod boss;
+pub mod dash;
+pub mod director;
+pub mod hit_feedback;
+pub mod hull;
+pub mod input;
+pub mod pickups;
+pub mod replay;
+pub mod rng;
+pub mod ron_file;
+mod spawn_asteroids;
+mod spawn_doublers;
+pub mod steering;
+pub mod torus;
+pub mod waves;
+pub mod weapons;
+pub mod wrap_ghost;
+use animation::Animation;
+use dash::{Dash, DashDisplay};
+use director::Director;
+use hull::{Hull, HullConfig, HullDisplay};
+use input::{InputScript, PlayerInput};
+use pickups::{PowerUpDisplay, PowerUps};
+use rng::GameRng;
+use spawn_asteroids::{AsteroidQueue, QueuedAsteroid};
+use spawn_doublers::{EnemyHelth, TowerQueue, TowerTimer};
+use waves::{WaveQueuers, WaveSchedule};
+use weapons::{Gun, WeaponDisplay, Weapons};
+use wrap_ghost::{SpriteCopy, WrapGhost};
+
+#[derive(Component)]
+struct DeathScreenUi;
+
+#[derive(Component)]
+struct TitleScreenUi;
+
+#[derive(Component)]
+struct PauseScreenUi;
+
+#[derive(Component)]
+struct DebugRec;
+
+#[derive(Component)]
+pub struct Player;
+
+#[derive(Component)]
+pub struct DropAfter {
+    time: f32,
+}
+
+#[derive(Component)]
+struct ScoreDisplay;
+
+#[derive(Component)]
+struct TimeDisplay;
+
+/// Size the board starts a run at, before towers shrink it.
+pub const FULL_BOARD: f32 = 800.0;
+
+/// Where the player starts a run.
+const PLAYER_START: Vec3 = Vec3::new(0., 100., 0.);
+
+#[derive(Resource)]
+pub struct BoardSize {
+    pub size: f32,
+}
+
+#[derive(Resource)]
+pub struct Score {
+    pub score: f32,
+}
+
+#[derive(Resource)]
+pub struct TimeCounter {
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameState {
+    TitleScreen,
+    Running,
+    Paused,
+    Died,
+    Won,
+}
+
+/// Steps of a frame, in order. Systems within a step that touch the same
+/// data or spawn entities are ordered among themselves too, so a frame
+/// plays out the same way every time and replays stay in sync.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameStep {
+    Input,
+    Spawn,
+    Steer,
+    Collide,
+    Board,
+    Wrap,
+}
+
+/// All of the gameplay: resources, enemies, physics and the HUD.
+/// Windowing, audio and device input are left to the caller, so the same
+/// systems run in the shipped game and in [`headless_app`].
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        let schedule = WaveSchedule::load();
+        app.insert_resource(BoardSize { size: FULL_BOARD })
+            .insert_resource(Score { score: 0.0 })
+            .insert_resource(TimeCounter { score: 0.0 })
+            .insert_resource(WaveQueuers::new(&schedule))
+            .insert_resource(schedule)
+            .add_state(GameState::TitleScreen)
+            .init_resource::<PlayerInput>()
+            .init_resource::<GameRng>()
+            .init_resource::<archetypes::Archetypes>()
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.))
+            .add_plugin(animation::AnimationPlugin)
+            .add_plugin(spawn_asteroids::SpawnTimesAsteroids)
+            .add_plugin(spawn_doublers::SpawnTimesDoublers)
+            .add_plugin(steering::SteeringPlugin)
+            .add_plugin(boss::BossPlugin)
+            .add_plugin(director::DirectorPlugin)
+            .add_plugin(hit_feedback::HitFeedbackPlugin)
+            .add_plugin(weapons::WeaponsPlugin)
+            .add_plugin(pickups::PickupsPlugin)
+            .add_plugin(hull::HullPlugin)
+            .add_plugin(dash::DashPlugin)
+            .add_plugin(wrap_ghost::WrapGhostPlugin)
+            .add_startup_system(spawn_base_cubes)
+            .add_startup_system(spawn_camera.after(spawn_base_cubes))
+            .add_startup_system(spawn_player.after(spawn_camera))
+            .add_startup_system(show_score.after(spawn_player))
+            .add_system_set(SystemSet::on_enter(GameState::TitleScreen).with_system(title_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::TitleScreen)
+                    .with_system(start_from_title.label(GameStep::Input)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::TitleScreen).with_system(clear_title_screen),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Running).with_system(start_run))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(handle_input.label(GameStep::Input))
+                    .with_system(queue_enemies.label(GameStep::Input).after(handle_input))
+                    .with_system(
+                        drop_them
+                            .label(GameStep::Collide)
+                            .after(hit_feedback::show_health_bars),
+                    )
+                    .with_system(update_time.label(GameStep::Board).after(GameStep::Collide)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(pause_screen))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(clear_pause_screen))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Died)
+                    .with_system(end_screen)
+                    .with_system(on_death.after(end_screen)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Won).with_system(end_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::Died).with_system(restart.label(GameStep::Input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Won).with_system(restart.label(GameStep::Input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Died).with_system(clear_end_screen))
+            .add_system_set(SystemSet::on_exit(GameState::Won).with_system(clear_end_screen))
+            .add_system(toggle_pause.label(GameStep::Input))
+            .add_system(
+                rezise_base_cube
+                    .label(GameStep::Wrap)
+                    .after(GameStep::Board),
+            )
+            .add_system(blink_system.label(GameStep::Wrap).after(rezise_base_cube))
+            .add_system(move_camera.label(GameStep::Wrap).after(blink_system));
+    }
+}
+
+/// Fixed amount the clock moves per update, see [`headless_app`].
+#[derive(Resource)]
+pub struct FrameTime(pub Duration);
+
+/// Builds the game without a window, renderer or audio. Every frame advances
+/// the clock by `frame_time` and takes the player's input from `script`, so
+/// a run is reproducible by calling `app.update()` in a loop. Like the real
+/// game it starts on the title screen; a frame with `restart` set begins the
+/// run.
+pub fn headless_app(script: InputScript, frame_time: Duration) -> App {
+    let mut app = headless_game();
+    app.insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()))
+        .insert_resource(FrameTime(frame_time))
+        .insert_resource(script)
+        .add_system_to_stage(CoreStage::PreUpdate, input::play_input_script)
+        .add_system_to_stage(CoreStage::Last, step_clock);
+    app
+}
+
+/// [`GamePlugin`] with just enough of bevy to run without a window.
+pub(crate) fn headless_game() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<Image>()
+        .add_asset::<Mesh>()
+        .add_asset::<TextureAtlas>()
+        .add_plugin(GamePlugin);
+    app
+}
+
+fn step_clock(mut strategy: ResMut<TimeUpdateStrategy>, frame_time: Res<FrameTime>) {
+    if let TimeUpdateStrategy::ManualInstant(instant) = *strategy {
+        *strategy = TimeUpdateStrategy::ManualInstant(instant + frame_time.0);
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle {
+        transform: Transform::from_xyz(0.0, 0.0, 100.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..Default::default()
+    });
+}
+
+fn move_camera(
+    mut camera: Query<(&mut Transform, With<Camera2d>, Without<Player>)>,
+    player: Query<&Transform, With<Player>>,
+) {
+    camera.single_mut().0.translation = player.single().translation;
+}
+
+fn spawn_base_cubes(
+    mut commands: Commands,
+    board_size: Res<BoardSize>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(board_size.size + 5.0, board_size.size + 5.0)),
+                ..default()
+            },
+            texture: asset_server.load("ground.png"),
+            transform: Transform::from_xyz(0.0, 0.0, -100.0),
+            ..default()
+        },
+        SpriteCopy,
+        DebugRec,
+        WrapGhost,
+    ));
+}
+
+fn rezise_base_cube(
+    mut debug_cubes: Query<(&mut Transform, &mut Sprite, With<DebugRec>)>,
+    board_size: Res<BoardSize>,
+) {
+    if board_size.is_changed() {
+        for mut cube in debug_cubes.iter_mut() {
+            cube.1.custom_size = Some(Vec2 {
+                x: board_size.size + 5.0,
+                y: board_size.size + 5.0,
+            })
+        }
+    }
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    weapons: Res<Weapons>,
+    hull: Res<HullConfig>,
+) {
+    commands.spawn((
+        Player,
+        Gun::new(0, &weapons.0[0]),
+        PowerUps::default(),
+        Hull::new(&hull),
+        Dash::default(),
+        SpriteBundle {
+            texture: asset_server.load("player.png"),
+            transform: Transform::from_translation(PLAYER_START),
+            ..Default::default()
+        },
+        RigidBody::Dynamic,
+        AdditionalMassProperties::Mass(1.),
+        GravityScale(0.),
+        Velocity {
+            linvel: Vec2 { x: 0.0, y: 0.0 },
+            angvel: 0.0,
+        },
+        Damping {
+            linear_damping: 2.0,
+            angular_damping: 0.5,
+        },
+        ExternalImpulse {
+            ..Default::default()
+        },
+        Collider::ball(8.),
+    ));
+}
+
+fn blink_system(mut query: Query<&mut Transform, Without<SpriteCopy>>, board_size: Res<BoardSize>) {
+    for mut transform in query.iter_mut() {
+        blink(&mut transform.translation, board_size.size);
+    }
+}
+
+fn blink(translation: &mut Vec3, board_size: f32) {
+    let position = translation.truncate();
+    let outside = position.abs().cmpgt(Vec2::splat(board_size / 2.0));
+    let wrapped = Vec2::select(
+        outside,
+        torus::wrap_position(position, board_size),
+        position,
+    );
+    *translation = wrapped.extend(0.);
+}
+
+fn handle_input(
+    time: Res<Time>,
+    input: Res<PlayerInput>,
+    mut query: Query<(&mut ExternalImpulse, &mut Transform, With<Player>)>,
+    mut board_size: ResMut<BoardSize>,
+) {
+    let forward = query.single().1.local_x();
+
+    if let Some(angle) = input.aim {
+        query.single_mut().1.rotation = Quat::from_rotation_z(-(angle + 1.571));
+    }
+
+    if input.shrink_board {
+        board_size.size -= 10.;
+    }
+
+    if input.grow_board {
+        board_size.size += 10.
+    }
+
+    if input.thrust {
+        let direction = input.thrust_direction.unwrap_or(Vec2 {
+            x: -forward.y,
+            y: forward.x,
+        });
+        query.single_mut().0.impulse = direction * 2000. * time.delta_seconds();
+    }
+}
+
+fn drop_them(mut commands: Commands, mut query: Query<(Entity, &mut DropAfter)>, time: Res<Time>) {
+    for mut q in query.iter_mut() {
+        if q.1.time > 0. {
+            q.1.time -= time.delta_seconds_f64() as f32;
+        } else {
+            commands.entity(q.0).despawn_recursive();
+        }
+    }
+}
+
+fn update_time(
+    mut score: ResMut<Score>,
+    mut time_counter: ResMut<TimeCounter>,
+    time: Res<Time>,
+    power_ups: Query<&PowerUps>,
+    mut score_display: Query<&mut Text, With<ScoreDisplay>>,
+    mut time_display: Query<(&mut Text, With<TimeDisplay>, Without<ScoreDisplay>)>,
+) {
+    time_counter.score += time.delta().as_secs_f32();
+    let multiplier: f32 = power_ups.iter().map(PowerUps::score_multiplier).product();
+    score.score += time.delta().as_secs_f32() * multiplier;
+    score_display.single_mut().sections[0].value = format!("Score: {}", score.score);
+    time_display.single_mut().0.sections[0].value = format!("Time:  {}", time_counter.score);
+}
+
+fn show_score(mut commands: Commands, score: Res<Score>, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("JetBrains Mono Medium Nerd Font Complete Mono.ttf");
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                max_size: Size::UNDEFINED,
+                flex_grow: 1.0,
+                justify_content: JustifyContent::FlexStart,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            hud_line(
+                parent,
+                &font,
+                ScoreDisplay,
+                format!("Score: {}", &score.score),
+            );
+            hud_line(
+                parent,
+                &font,
+                TimeDisplay,
+                format!("Time:  {}", &score.score),
+            );
+            hud_line(parent, &font, WeaponDisplay, "Gun:");
+            hud_line(parent, &font, HullDisplay, "Hull:");
+            hud_line(parent, &font, DashDisplay, "Dash:");
+            hud_line(parent, &font, PowerUpDisplay, "Power:");
+        });
+}
+
+/// One line of text in the HUD, kept up to date by whoever owns `marker`.
+fn hud_line(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    marker: impl Component,
+    label: impl Into<String>,
+) {
+    parent.spawn((
+        marker,
+        TextBundle::from_section(
+            label,
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                font: font.clone(),
+            },
+        )
+        .with_style(Style {
+            flex_shrink: 0.,
+            size: Size::new(Val::Undefined, Val::Px(20.)),
+            ..Default::default()
+        }),
+    ));
+}
+
+fn title_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size {
+                        width: Val::Auto,
+                        height: Val::Px(60.),
+                    },
+                    justify_content: JustifyContent::Center,
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            },
+            TitleScreenUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section(
+                "bevy-astro. r to start",
+                TextStyle {
+                    font_size: 70.0,
+                    color: Color::WHITE,
+                    font: asset_server.load("JetBrains Mono Medium Nerd Font Complete Mono.ttf"),
+                },
+            ),));
+        });
+}
+
+fn start_from_title(input: Res<PlayerInput>, mut game_state: ResMut<State<GameState>>) {
+    if input.restart {
+        let _ = game_state.set(GameState::Running);
+    }
+}
+
+fn clear_title_screen(mut commands: Commands, title_screen: Query<Entity, With<TitleScreenUi>>) {
+    for ui in title_screen.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn end_screen(
+    mut commands: Commands,
+    score: Res<Score>,
+    asset_server: Res<AssetServer>,
+    game_state: Res<State<GameState>>,
+) {
+    let title = match game_state.current() {
+        GameState::Died => "nah you bad. r to restart",
+        _ => "gg",
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size {
+                        width: Val::Auto,
+                        height: Val::Px(60.),
+                    },
+                    justify_content: JustifyContent::Center,
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            },
+            DeathScreenUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section(
+                format!("{title}, Score: {}", &score.score),
+                TextStyle {
+                    font_size: 70.0,
+                    color: Color::WHITE,
+                    font: asset_server.load("JetBrains Mono Medium Nerd Font Complete Mono.ttf"),
+                },
+            ),));
+        });
+}
+
+fn clear_end_screen(mut commands: Commands, end_screen: Query<Entity, With<DeathScreenUi>>) {
+    for ui in end_screen.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+/// Not part of a state's systems, those run again after every transition
+/// in the same frame and would toggle back and forth while pause is held.
+fn toggle_pause(input: Res<PlayerInput>, mut game_state: ResMut<State<GameState>>) {
+    if !input.pause {
+        return;
+    }
+    let _ = match game_state.current() {
+        GameState::Running => game_state.push(GameState::Paused),
+        GameState::Paused => game_state.pop(),
+        _ => Ok(()),
+    };
+}
+
+fn pause_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut rapier: ResMut<RapierConfiguration>,
+) {
+    rapier.physics_pipeline_active = false;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size {
+                        width: Val::Auto,
+                        height: Val::Px(60.),
+                    },
+                    justify_content: JustifyContent::Center,
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            },
+            PauseScreenUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section(
+                "paused",
+                TextStyle {
+                    font_size: 70.0,
+                    color: Color::WHITE,
+                    font: asset_server.load("JetBrains Mono Medium Nerd Font Complete Mono.ttf"),
+                },
+            ),));
+        });
+}
+
+fn clear_pause_screen(
+    mut commands: Commands,
+    pause_screen: Query<Entity, With<PauseScreenUi>>,
+    mut rapier: ResMut<RapierConfiguration>,
+) {
+    rapier.physics_pipeline_active = true;
+    for ui in pause_screen.iter() {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn queue_enemies(
+    schedule: Res<WaveSchedule>,
+    mut queuers: ResMut<WaveQueuers>,
+    time: Res<Time>,
+    mut as_que: ResMut<AsteroidQueue>,
+    mut tower_queue: ResMut<TowerQueue>,
+    board_size: Res<BoardSize>,
+    player: Query<&Transform, With<Player>>,
+    director: Res<Director>,
+    mut rng: ResMut<GameRng>,
+) {
+    let difficulty = director.difficulty;
+    let player = player
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    for delay in queuers
+        .asteroids
+        .tick(&schedule.asteroids, time.delta(), difficulty)
+    {
+        let enemy = schedule.asteroids.pick(difficulty, &mut rng.asteroids);
+        as_que.queue.push(QueuedAsteroid::new(
+            enemy,
+            delay,
+            board_size.size,
+            player,
+            &mut rng.asteroids,
+        ));
+    }
+    for delay in queuers
+        .towers
+        .tick(&schedule.towers, time.delta(), difficulty)
+    {
+        let enemy = schedule.towers.pick(difficulty, &mut rng.towers);
+        tower_queue.queue.push(TowerTimer::new(
+            enemy,
+            delay,
+            &board_size.size,
+            &mut rng.towers,
+        ));
+    }
+}
+
+fn on_death(
+    mut board_size: ResMut<BoardSize>,
+    mut score: ResMut<Score>,
+    mut time_counter: ResMut<TimeCounter>,
+) {
+    board_size.size = -500.0;
+
+    score.score = 0.0;
+    time_counter.score = 0.0;
+}
+
+fn restart(input: Res<PlayerInput>, mut game_state: ResMut<State<GameState>>) {
+    if input.restart {
+        let _ = game_state.set(GameState::Running);
+    }
+}
+
+fn start_run(
+    mut commands: Commands,
+    leftovers: Query<Entity, Or<(With<EnemyHelth>, With<DropAfter>, With<Animation>)>>,
+    mut player: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut ExternalImpulse,
+            &mut Gun,
+        ),
+        With<Player>,
+    >,
+    weapons: Res<Weapons>,
+    mut board_size: ResMut<BoardSize>,
+    schedule: Res<WaveSchedule>,
+    mut queuers: ResMut<WaveQueuers>,
+    mut as_que: ResMut<AsteroidQueue>,
+    mut tower_queue: ResMut<TowerQueue>,
+    mut score: ResMut<Score>,
+    mut time_counter: ResMut<TimeCounter>,
+    mut rng: ResMut<GameRng>,
+) {
+    for leftover in leftovers.iter() {
+        commands.entity(leftover).despawn_recursive();
+    }
+
+    // Nothing of how the last run ended carries over, so a run plays out
+    // the same from its seed.
+    for (mut transform, mut velocity, mut impulse, mut gun) in player.iter_mut() {
+        *transform = Transform::from_translation(PLAYER_START);
+        *velocity = Velocity::zero();
+        *impulse = ExternalImpulse::default();
+        *gun = Gun::new(0, &weapons.0[0]);
+    }
+
+    as_que.queue = vec![];
+    tower_queue.queue = vec![];
+
+    *queuers = WaveQueuers::new(&schedule);
+
+    score.score = 0.0;
+    time_counter.score = 0.0;
+
+    rng.restart();
+    info!("seed: {}", rng.seed());
+
+    board_size.size = FULL_BOARD;
+}
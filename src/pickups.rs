@@ -0,0 +1,357 @@
+use std::{f32::consts::FRAC_PI_4, mem};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::archetypes::Archetypes;
+use crate::hull::Hull;
+use crate::rng::GameRng;
+use crate::ron_file::load_ron;
+use crate::spawn_asteroids::EnemyDestroyed;
+use crate::wrap_ghost::{owner_collisions, SeamCollider, WrapCollider, WrapGhost};
+use crate::{DropAfter, GameState, GameStep, Player};
+
+/// How fast pickups and the invulnerable player blink.
+const BLINK_RATE: f32 = 8.0;
+
+const SHIELD_COLOR: Color = Color::rgb(0.5, 0.75, 1.0);
+
+/// What a pickup does for the player while it lasts.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum Effect {
+    RapidFire(f32),
+    Shield,
+    GrowBoard(f32),
+    ScoreMultiplier(f32),
+    Invulnerable,
+}
+
+impl Effect {
+    fn name(&self) -> &'static str {
+        match self {
+            Effect::RapidFire(_) => "rapid fire",
+            Effect::Shield => "shield",
+            Effect::GrowBoard(_) => "big board",
+            Effect::ScoreMultiplier(_) => "score bonus",
+            Effect::Invulnerable => "invulnerable",
+        }
+    }
+}
+
+/// One kind of pickup, see `assets/pickups.ron`.
+#[derive(Deserialize, Clone)]
+pub struct PickupKind {
+    pub effect: Effect,
+    pub duration: f32,
+    pub weight: f32,
+    pub color: (f32, f32, f32),
+}
+
+#[derive(Resource, Deserialize, Clone)]
+pub struct Pickups {
+    pub lifetime: f32,
+    pub warning: f32,
+    pub radius: f32,
+    pub pickups: Vec<PickupKind>,
+}
+
+impl Pickups {
+    pub fn load() -> Self {
+        load_ron("pickups.ron", include_str!("../assets/pickups.ron"))
+    }
+
+    /// A kind picked by weight, `None` if there are none.
+    fn pick(&self, rng: &mut impl Rng) -> Option<usize> {
+        let total: f32 = self.pickups.iter().map(|kind| kind.weight.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0.0..total);
+        self.pickups.iter().position(|kind| {
+            roll -= kind.weight.max(0.0);
+            roll < 0.0
+        })
+    }
+}
+
+/// Lying on the board, waiting to be picked up.
+#[derive(Component)]
+pub struct Pickup {
+    /// Index into [`Pickups::pickups`].
+    kind: usize,
+}
+
+/// Effects the player has picked up, with the time they have left.
+#[derive(Component, Default)]
+pub struct PowerUps {
+    active: Vec<(Effect, Timer)>,
+}
+
+impl PowerUps {
+    fn give(&mut self, effect: Effect, duration: f32) {
+        self.active
+            .retain(|(active, _)| mem::discriminant(active) != mem::discriminant(&effect));
+        self.active
+            .push((effect, Timer::from_seconds(duration, TimerMode::Once)));
+    }
+
+    fn effects(&self) -> impl Iterator<Item = Effect> + '_ {
+        self.active.iter().map(|(effect, _)| *effect)
+    }
+
+    /// How many times as fast the player's weapons fire.
+    pub fn fire_rate(&self) -> f32 {
+        self.effects()
+            .map(|effect| match effect {
+                Effect::RapidFire(rate) => rate,
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    pub fn score_multiplier(&self) -> f32 {
+        self.effects()
+            .map(|effect| match effect {
+                Effect::ScoreMultiplier(factor) => factor,
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    /// How much bigger the board is.
+    pub fn board_bonus(&self) -> f32 {
+        self.effects()
+            .map(|effect| match effect {
+                Effect::GrowBoard(size) => size,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        self.effects()
+            .any(|effect| matches!(effect, Effect::Invulnerable))
+    }
+
+    /// Whether a hit is shrugged off. A shield that takes it is used up.
+    pub fn absorb_hit(&mut self) -> bool {
+        if self.invulnerable() {
+            return true;
+        }
+        let shields = self.active.len();
+        self.active
+            .retain(|(effect, _)| !matches!(effect, Effect::Shield));
+        self.active.len() < shields
+    }
+}
+
+/// Lists the player's effects in the HUD.
+#[derive(Component)]
+pub struct PowerUpDisplay;
+
+pub struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Pickups::load())
+            .add_system_set(SystemSet::on_enter(GameState::Running).with_system(reset_power_ups))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(
+                        drop_pickups
+                            .label(GameStep::Collide)
+                            .after(crate::drop_them),
+                    )
+                    .with_system(collect_pickups.label(GameStep::Collide).after(drop_pickups))
+                    .with_system(
+                        tick_power_ups
+                            .label(GameStep::Board)
+                            .after(crate::spawn_doublers::time_and_adjust_board),
+                    )
+                    .with_system(
+                        blink_pickups
+                            .label(GameStep::Board)
+                            .after(GameStep::Collide),
+                    ),
+            )
+            .add_system(show_power_ups);
+    }
+}
+
+fn reset_power_ups(mut power_ups: Query<&mut PowerUps>) {
+    for mut power_ups in power_ups.iter_mut() {
+        *power_ups = PowerUps::default();
+    }
+}
+
+fn drop_pickups(
+    mut commands: Commands,
+    mut destroyed: EventReader<EnemyDestroyed>,
+    pickups: Res<Pickups>,
+    archetypes: Res<Archetypes>,
+    mut rng: ResMut<GameRng>,
+) {
+    for enemy in destroyed.iter() {
+        let drops = archetypes
+            .get(&enemy.archetype)
+            .map_or(0.0, |archetype| archetype.drops);
+        if rng.pickups.gen::<f32>() >= drops {
+            continue;
+        }
+        let Some(kind) = pickups.pick(&mut rng.pickups) else {
+            continue;
+        };
+        let (r, g, b) = pickups.pickups[kind].color;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(r, g, b),
+                    custom_size: Some(Vec2::splat(pickups.radius * 1.4)),
+                    ..default()
+                },
+                transform: Transform::from_translation(enemy.position.extend(0.))
+                    .with_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+                ..default()
+            },
+            Pickup { kind },
+            Collider::ball(pickups.radius),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            DropAfter {
+                time: pickups.lifetime,
+            },
+            WrapGhost,
+            WrapCollider,
+        ));
+    }
+}
+
+pub(crate) fn collect_pickups(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    mut player: Query<(Entity, &mut PowerUps), With<Player>>,
+    lying: Query<&Pickup>,
+    seams: Query<(), With<SeamCollider>>,
+    pickups: Res<Pickups>,
+) {
+    let Ok((player, mut power_ups)) = player.get_single_mut() else {
+        return;
+    };
+    let mut collected = vec![];
+    for (a, b) in owner_collisions(&mut collisions, &seams) {
+        let other = match (a == player, b == player) {
+            (true, _) => b,
+            (_, true) => a,
+            _ => continue,
+        };
+        let Ok(pickup) = lying.get(other) else {
+            continue;
+        };
+        if collected.contains(&other) {
+            continue;
+        }
+        collected.push(other);
+        let kind = &pickups.pickups[pickup.kind];
+        power_ups.give(kind.effect, kind.duration);
+        commands.entity(other).despawn_recursive();
+    }
+}
+
+fn tick_power_ups(time: Res<Time>, mut power_ups: Query<&mut PowerUps>) {
+    for mut power_ups in power_ups.iter_mut() {
+        power_ups
+            .active
+            .retain_mut(|(_, timer)| !timer.tick(time.delta()).finished());
+    }
+}
+
+/// Pickups about to go blink.
+fn blink_pickups(
+    pickups: Res<Pickups>,
+    mut lying: Query<(&DropAfter, &mut Visibility), With<Pickup>>,
+) {
+    for (drop_after, mut visibility) in lying.iter_mut() {
+        visibility.is_visible = drop_after.time > pickups.warning || blink_on(drop_after.time);
+    }
+}
+
+fn blink_on(time_left: f32) -> bool {
+    (time_left * BLINK_RATE) as i32 % 2 == 0
+}
+
+/// Tints the player while shielded, blinks it while invulnerable, from a
+/// pickup or a recent hit, and lists what's active in the HUD.
+fn show_power_ups(
+    mut player: Query<(&PowerUps, Option<&Hull>, &mut Sprite), With<Player>>,
+    mut display: Query<&mut Text, With<PowerUpDisplay>>,
+) {
+    let Ok((power_ups, hull, mut sprite)) = player.get_single_mut() else {
+        return;
+    };
+    let invulnerable = power_ups
+        .active
+        .iter()
+        .filter(|(effect, _)| matches!(effect, Effect::Invulnerable))
+        .map(|(_, timer)| timer.remaining_secs())
+        .chain(hull.map(Hull::invulnerable))
+        .fold(0.0, f32::max);
+    let mut color = if power_ups
+        .effects()
+        .any(|effect| matches!(effect, Effect::Shield))
+    {
+        SHIELD_COLOR
+    } else {
+        Color::WHITE
+    };
+    if invulnerable > 0.0 {
+        color.set_a(if blink_on(invulnerable) { 1.0 } else { 0.3 });
+    }
+    if sprite.color != color {
+        sprite.color = color;
+    }
+
+    let listed = power_ups
+        .active
+        .iter()
+        .map(|(effect, timer)| format!("{} {:.0}", effect.name(), timer.remaining_secs().ceil()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    for mut text in display.iter_mut() {
+        let value = format!("Power: {listed}");
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absorb_hit_uses_up_a_shield() {
+        let mut power_ups = PowerUps::default();
+        power_ups.give(Effect::RapidFire(2.0), 10.0);
+        power_ups.give(Effect::Shield, 10.0);
+        assert!(power_ups.absorb_hit());
+        assert!(!power_ups.absorb_hit());
+        assert_eq!(power_ups.fire_rate(), 2.0);
+    }
+
+    #[test]
+    fn absorb_hit_while_invulnerable_keeps_the_shield() {
+        let mut power_ups = PowerUps::default();
+        power_ups.give(Effect::Invulnerable, 10.0);
+        power_ups.give(Effect::Shield, 10.0);
+        assert!(power_ups.absorb_hit());
+        assert!(power_ups.absorb_hit());
+        assert_eq!(power_ups.active.len(), 2);
+    }
+
+    #[test]
+    fn absorb_hit_without_effects() {
+        assert!(!PowerUps::default().absorb_hit());
+    }
+}
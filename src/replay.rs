@@ -0,0 +1,148 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*, time::TimeUpdateStrategy, utils::Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{self, InputScript, PlayerInput},
+    rng::GameRng,
+    GameState,
+};
+
+/// Everything needed to play a session again: how the rng was seeded and,
+/// for every update since the game started, how much time passed and what
+/// the player did.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub pinned: bool,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub delta: Duration,
+    pub input: PlayerInput,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        ron::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let text = ron::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// Records the session into a [`Replay`] and writes it to `path` whenever a
+/// run ends and when the game is closed.
+pub struct RecordReplayPlugin {
+    pub path: PathBuf,
+}
+
+#[derive(Resource)]
+struct ReplayPath(PathBuf);
+
+impl Plugin for RecordReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .insert_resource(ReplayPath(self.path.clone()))
+            .add_startup_system(start_recording)
+            .add_system_to_stage(CoreStage::Last, record_frame)
+            .add_system_to_stage(CoreStage::Last, save_replay.after(record_frame));
+    }
+}
+
+fn start_recording(mut replay: ResMut<Replay>, rng: Res<GameRng>) {
+    *replay = Replay {
+        seed: rng.session_seed(),
+        pinned: rng.pinned(),
+        frames: vec![],
+    };
+}
+
+fn record_frame(mut replay: ResMut<Replay>, time: Res<Time>, input: Res<PlayerInput>) {
+    replay.frames.push(ReplayFrame {
+        delta: time.delta(),
+        input: input.clone(),
+    });
+}
+
+fn save_replay(
+    replay: Res<Replay>,
+    path: Res<ReplayPath>,
+    game_state: Res<State<GameState>>,
+    mut last_state: Local<Option<GameState>>,
+    exit: EventReader<AppExit>,
+) {
+    let state = game_state.current().clone();
+    let run_ended =
+        last_state.as_ref() != Some(&state) && matches!(state, GameState::Died | GameState::Won);
+    *last_state = Some(state);
+    if !run_ended && exit.is_empty() {
+        return;
+    }
+    match replay.save(&path.0) {
+        Ok(()) => info!("replay saved to {}", path.0.display()),
+        Err(e) => error!("could not save replay: {e}"),
+    }
+}
+
+/// Plays a [`Replay`] back: seeds the rng, feeds the recorded input and steps
+/// the clock by the recorded deltas. Once the replay runs out the clock goes
+/// back to real time and the player lets go of everything.
+pub struct ReplayPlaybackPlugin(pub Replay);
+
+/// Deltas of the frames still to come.
+#[derive(Resource)]
+struct ReplayClock(VecDeque<Duration>);
+
+impl Plugin for ReplayPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        let replay = &self.0;
+        app.insert_resource(GameRng::session(replay.seed, replay.pinned))
+            .insert_resource(InputScript::new(
+                replay.frames.iter().map(|frame| frame.input.clone()),
+            ))
+            // The first update always takes no time.
+            .insert_resource(ReplayClock(
+                replay
+                    .frames
+                    .iter()
+                    .skip(1)
+                    .map(|frame| frame.delta)
+                    .collect(),
+            ))
+            .insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()))
+            .add_system_to_stage(CoreStage::PreUpdate, input::play_input_script)
+            .add_system_to_stage(CoreStage::Last, step_replay_clock);
+    }
+}
+
+fn step_replay_clock(mut strategy: ResMut<TimeUpdateStrategy>, mut clock: ResMut<ReplayClock>) {
+    let TimeUpdateStrategy::ManualInstant(instant) = *strategy else {
+        return;
+    };
+    *strategy = match clock.0.pop_front() {
+        Some(delta) => TimeUpdateStrategy::ManualInstant(instant + delta),
+        None => TimeUpdateStrategy::Automatic,
+    };
+}
+
+/// Like [`headless_app`](crate::headless_app), but driven by `replay`. Runs
+/// for `replay.frames.len()` updates.
+pub fn headless_replay(replay: Replay) -> App {
+    let mut app = crate::headless_game();
+    app.add_plugin(ReplayPlaybackPlugin(replay));
+    app
+}
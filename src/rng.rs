@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Source of every random decision in a run. Each subsystem draws from its
+/// own stream, so an extra roll in one place doesn't shift the others and a
+/// run can be replayed from its seed alone. Unpinned runs take their seeds
+/// from a session stream, so a whole session replays from its first seed.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    session_seed: u64,
+    pinned: bool,
+    session: StdRng,
+    pub shots: StdRng,
+    pub towers: StdRng,
+    pub projectiles: StdRng,
+    pub asteroids: StdRng,
+    pub pickups: StdRng,
+    pub respawns: StdRng,
+}
+
+impl GameRng {
+    /// Every run, including restarts, uses `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self::session(seed, true)
+    }
+
+    /// Every run gets a fresh seed.
+    pub fn from_entropy() -> Self {
+        Self::session(rand::random(), false)
+    }
+
+    /// Picks up a session from [`session_seed`](Self::session_seed) and
+    /// [`pinned`](Self::pinned).
+    pub fn session(session_seed: u64, pinned: bool) -> Self {
+        let mut session = StdRng::seed_from_u64(session_seed);
+        let seed = if pinned { session_seed } else { session.gen() };
+        GameRng {
+            session_seed,
+            pinned,
+            session,
+            ..Self::streams(seed)
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn session_seed(&self) -> u64 {
+        self.session_seed
+    }
+
+    pub fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Rewinds all streams for a new run.
+    pub fn restart(&mut self) {
+        let seed = if self.pinned {
+            self.seed
+        } else {
+            self.session.gen()
+        };
+        let GameRng {
+            shots,
+            towers,
+            projectiles,
+            asteroids,
+            pickups,
+            respawns,
+            ..
+        } = Self::streams(seed);
+        self.seed = seed;
+        self.shots = shots;
+        self.towers = towers;
+        self.projectiles = projectiles;
+        self.asteroids = asteroids;
+        self.pickups = pickups;
+        self.respawns = respawns;
+    }
+
+    fn streams(seed: u64) -> Self {
+        let stream = |i: u64| StdRng::seed_from_u64(seed ^ i.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        GameRng {
+            seed,
+            session_seed: seed,
+            pinned: false,
+            session: stream(0),
+            shots: stream(1),
+            towers: stream(2),
+            projectiles: stream(3),
+            asteroids: stream(4),
+            pickups: stream(5),
+            respawns: stream(6),
+        }
+    }
+}
+
+/// Pinned to `ASTRO_SEED` when it is set, random otherwise.
+impl Default for GameRng {
+    fn default() -> Self {
+        match std::env::var("ASTRO_SEED")
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            Some(seed) => GameRng::new(seed),
+            None => GameRng::from_entropy(),
+        }
+    }
+}
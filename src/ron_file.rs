@@ -0,0 +1,39 @@
+use std::fs;
+
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::de::DeserializeOwned;
+
+/// Reads `file` from the asset folder, so the game can be tuned without a
+/// rebuild. `builtin` is the shipped copy of the file, passed in with
+/// `include_str!`, and is used when the file can't be read or doesn't parse.
+/// Panics only when `builtin` doesn't parse.
+pub fn load_ron<T: DeserializeOwned>(file: &str, builtin: &str) -> T {
+    let path = FileAssetIo::get_base_path().join("assets").join(file);
+    let parsed = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| ron::from_str(&text).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("using the built-in {file}, {}: {e}", path.display());
+            ron::from_str(builtin).unwrap_or_else(|e| panic!("bad built-in {file}: {e}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_falls_back_to_builtin() {
+        let value: Vec<u32> = load_ron("no-such-file.ron", "[1, 2]");
+        assert_eq!(value, vec![1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bad built-in")]
+    fn bad_builtin_panics() {
+        let _: Vec<u32> = load_ron("no-such-file.ron", "[1,");
+    }
+}
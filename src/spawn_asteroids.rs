@@ -1,413 +1,304 @@
-use std::ops::Neg;
+use std::f32::consts::TAU;
+use std::time::Duration;
 
 use bevy::prelude::*;
-use bevy_rapier2d::parry::query;
 use bevy_rapier2d::prelude::*;
-use bevy_rapier2d::prelude::LockedAxes;
+use rand::Rng;
 
-use crate::CurrentGame;
-use crate::GameState;
+use crate::animation::{self, Animation};
+use crate::archetypes::{Archetypes, Enemy};
+use crate::boss::Boss;
+use crate::rng::GameRng;
 use crate::spawn_doublers::EnemyHelth;
 use crate::spawn_doublers::TowerField;
+use crate::torus;
+use crate::weapons::PlayerShot;
+use crate::wrap_ghost::{owner_collisions, SeamCollider, WrapGhost};
+use crate::{GameState, GameStep};
 
 use super::BoardSize;
 use super::DropAfter;
 use super::Player;
-use super::Shot;
 
-#[derive(Component)]
-pub struct SpriteCopy;
+#[derive(Resource)]
+pub struct AsteroidQueue {
+    pub queue: Vec<QueuedAsteroid>,
+}
 
-#[derive(Component)]
-pub struct SpriteClone {
-    pub x: i32,
-    pub y: i32,
+pub struct QueuedAsteroid {
+    /// Archetype to spawn.
+    enemy: String,
+    timer: Timer,
+    position: Vec2,
+    telegraphed: bool,
 }
 
-#[derive(Resource)]
-pub struct AsteroidQueue {
-    pub queue: Vec<Timer>,
+impl QueuedAsteroid {
+    /// An `enemy` that shows up after `delay` on whichever of a few random
+    /// spots is furthest from the player.
+    pub fn new(enemy: &str, delay: f32, board_size: f32, player: Vec2, rng: &mut impl Rng) -> Self {
+        QueuedAsteroid {
+            enemy: enemy.to_string(),
+            timer: Timer::from_seconds(delay, TimerMode::Once),
+            position: torus::furthest_spot(&[player], SPAWN_CANDIDATES, board_size, rng),
+            telegraphed: false,
+        }
+    }
 }
 
-#[derive(Component)]
-pub struct Factory(pub Timer);
+/// An enemy was shot down.
+pub struct EnemyDestroyed {
+    /// Name of its archetype.
+    pub archetype: String,
+    pub position: Vec2,
+}
 
+/// A shot hit an enemy, whether or not it survived.
+pub struct EnemyHit {
+    pub enemy: Entity,
+    /// Of the shot.
+    pub velocity: Vec2,
+}
+
+/// Blinks where an asteroid is about to show up, faster the closer it is.
 #[derive(Component)]
-pub struct FactoryParent;
+struct SpawnMarker(Timer);
 
 pub struct SpawnTimesAsteroids;
 
 impl Plugin for SpawnTimesAsteroids {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(test_add_to_queue)
-            .add_startup_system(build_factory)
-            .insert_resource(AsteroidQueue { queue: vec![] })
-            .add_system(animate_sprite)
-            .add_system(fix_visibility_factory)
-            .add_system(update_spawn_queue)
-            .add_system(correct_child_on_size_change)
-            .add_system(target_move_player)
-            .add_system(check_shooted);
+        app.insert_resource(AsteroidQueue { queue: vec![] })
+            .add_event::<EnemyDestroyed>()
+            .add_event::<EnemyHit>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(
+                        update_spawn_queue
+                            .label(GameStep::Spawn)
+                            .after(GameStep::Input)
+                            .after(crate::spawn_doublers::spawn_projectile),
+                    )
+                    .with_system(
+                        telegraph_spawns
+                            .label(GameStep::Spawn)
+                            .after(update_spawn_queue),
+                    )
+                    .with_system(
+                        check_shooted
+                            .label(GameStep::Collide)
+                            .after(GameStep::Steer),
+                    ),
+            );
     }
 }
 
 #[derive(Component)]
 pub struct Asteroid;
 
-#[derive(Component, Deref, DerefMut)]
-struct AnimationTimer(Timer);
+/// How fast fragments fly apart, on top of the parent's velocity.
+const FRAGMENT_SPREAD_SPEED: f32 = 60.0;
 
-fn test_add_to_queue(mut queue: ResMut<AsteroidQueue>) {
-    // queue.queue.push(Timer::from_seconds(5., TimerMode::Once));
-}
+/// Spots tried per spawn, the one furthest from the player wins.
+const SPAWN_CANDIDATES: usize = 8;
 
-fn fix_visibility_factory(
-    mut query: Query<(&mut Factory, &mut Visibility)>,
-    mut queue: Res<AsteroidQueue>,
+/// How long before an asteroid shows up its marker appears.
+const TELEGRAPH_TIME: f32 = 2.0;
+
+fn telegraph_spawns(
+    mut commands: Commands,
+    mut queue: ResMut<AsteroidQueue>,
+    mut markers: Query<(&mut SpawnMarker, &DropAfter, &mut Visibility)>,
+    archetypes: Res<Archetypes>,
     time: Res<Time>,
 ) {
-    let mut min_time: f32 = 5.0;
-    for timer in queue.queue.iter() {
-        if timer.remaining_secs() < min_time {
-            min_time = timer.remaining_secs();
-        }
-    }
-
-    if min_time > 2.0 {
-        for mut elem in query.iter_mut() {
-            *elem.1 = Visibility { is_visible: false };
-        }
-        return;
-    }
-
-    for mut elem in query.iter_mut() {
-        if elem.0 .0.tick(time.delta()).just_finished() {
-            elem.1.toggle();
+    for queued in queue.queue.iter_mut() {
+        if queued.telegraphed || queued.timer.remaining_secs() > TELEGRAPH_TIME {
+            continue;
         }
+        queued.telegraphed = true;
+        let Some(mut sprite) = archetypes.sprite(&queued.enemy) else {
+            continue;
+        };
+        sprite.transform = Transform::from_translation(queued.position.extend(0.0));
+        commands.spawn((
+            sprite,
+            SpawnMarker(Timer::from_seconds(0.3, TimerMode::Repeating)),
+            DropAfter {
+                time: queued.timer.remaining_secs(),
+            },
+            WrapGhost,
+        ));
     }
-}
 
-fn build_factory(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_adlases: ResMut<Assets<TextureAtlas>>,
-    board_size: Res<BoardSize>,
-) {
-    let texture_handle = asset_server.load("asteroid.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2 { x: 31.0, y: 31.0 }, 2, 2, None, None);
-    let texture_atlas_handle = texture_adlases.add(texture_atlas);
-
-    let mut astroid_copies: Vec<Entity> = vec![];
-
-    let rows = 10;
-    let column = 5;
-    let offset_rows = board_size.size * ((rows - 1) / 2) as f32; // 2*size - placex * size
-    let offset_column = board_size.size * ((column - 1) / 2) as f32; // size
-
-    for place_x in 0..rows {
-        for place_y in 0..column {
-            astroid_copies.push(
-                commands
-                    .spawn((
-                        // SpriteSheetBundle {
-                        //     texture_atlas: texture_atlas_handle.clone(),
-                        //     transform: Transform::from_xyz(
-                        //         offset_rows - (place_x as f32 * board_size.size),
-                        //         offset_column - (place_y as f32 * board_size.size),
-                        //         0.,
-                        //     ),
-                        //     ..Default::default()
-                        // },
-                        SpriteBundle {
-                            texture: asset_server.load("buggy.png"),
-                            sprite: Sprite { custom_size: Some(Vec2{ x: 55.0, y: 55.0}), ..default() },
-                            transform: Transform::from_xyz(
-                                offset_rows - (place_x as f32 * board_size.size),
-                                offset_column - (place_y as f32 * board_size.size),
-                                0.,
-                            ),
-                            ..Default::default()
-                        },
-                        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                        SpriteCopy,
-                        Factory(Timer::from_seconds(0.3, TimerMode::Repeating)),
-                        SpriteClone {
-                            x: place_x,
-                            y: place_y,
-                        },
-                    ))
-                    .id(),
-            )
+    for (mut marker, drop_after, mut visibility) in markers.iter_mut() {
+        let left = (drop_after.time / TELEGRAPH_TIME).clamp(0.0, 1.0);
+        marker
+            .0
+            .set_duration(Duration::from_secs_f32(0.05 + 0.25 * left));
+        if marker.0.tick(time.delta()).just_finished() {
+            visibility.toggle();
         }
     }
-
-    commands
-        .spawn((
-            // SpriteSheetBundle {
-            //     texture_atlas: texture_atlas_handle,
-            //     transform: Transform::from_xyz(10., 10., 0.),
-            //     ..Default::default()
-            // },
-            SpatialBundle {
-                transform: Transform::from_xyz(10., 10., 0.),
-                ..Default::default()
-            },
-            FactoryParent,
-            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        ))
-        .push_children(&astroid_copies);
 }
 
 fn update_spawn_queue(
     mut queue: ResMut<AsteroidQueue>,
     time: Res<Time>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_adlases: ResMut<Assets<TextureAtlas>>,
-    board_size: Res<BoardSize>,
+    archetypes: Res<Archetypes>,
+    mut rng: ResMut<GameRng>,
 ) {
-    for (i, timer) in queue.queue.iter_mut().enumerate() {
-        if timer.tick(time.delta()).finished() {
-            spawn_one_asteroid(commands, asset_server, texture_adlases, board_size);
+    for (i, queued) in queue.queue.iter_mut().enumerate() {
+        if queued.timer.tick(time.delta()).finished() {
+            archetypes.spawn(
+                &mut commands,
+                &mut rng.asteroids,
+                &queued.enemy,
+                queued.position,
+                Vec2 { x: 50.0, y: 80.0 },
+            );
             queue.queue.swap_remove(i);
             break; // hmm hacky
         }
     }
 }
 
-fn spawn_one_asteroid(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_adlases: ResMut<Assets<TextureAtlas>>,
-    board_size: Res<BoardSize>,
+/// Replaces a destroyed enemy with its fragments, spread evenly around it
+/// and carrying on with its velocity.
+fn split_enemy(
+    commands: &mut Commands,
+    archetypes: &Archetypes,
+    rng: &mut GameRng,
+    enemy: &Enemy,
+    transform: &Transform,
+    velocity: Option<&Velocity>,
 ) {
-    let texture_handle = asset_server.load("asteroid.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2 { x: 31.0, y: 31.0 }, 2, 2, None, None);
-    let texture_atlas_handle = texture_adlases.add(texture_atlas);
-
-    let mut astroid_copies: Vec<Entity> = vec![];
-
-    let rows = 10;
-    let column = 5;
-    let offset_rows = board_size.size * ((rows - 1) / 2) as f32; // 2*size - placex * size
-    let offset_column = board_size.size * ((column - 1) / 2) as f32; // size
-
-    for place_x in 0..rows {
-        for place_y in 0..column {
-            astroid_copies.push(
-                commands
-                    .spawn((
-                        // SpriteSheetBundle {
-                        //     texture_atlas: texture_atlas_handle.clone(),
-                        //     transform: Transform::from_xyz(
-                        //         offset_rows - (place_x as f32 * board_size.size),
-                        //         offset_column - (place_y as f32 * board_size.size),
-                        //         0.,
-                        //     ),
-                        //     ..Default::default()
-                        // },
-                        SpriteBundle {
-                            texture: asset_server.load("buggy.png"),
-                            sprite: Sprite { custom_size: Some(Vec2{ x: 55.0, y: 55.0}), ..default() },
-                            transform: Transform::from_xyz(
-                                offset_rows - (place_x as f32 * board_size.size),
-                                offset_column - (place_y as f32 * board_size.size),
-                                0.,
-                            ),
-                            ..Default::default()
-                        },
-                        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                        SpriteCopy,
-                        SpriteClone {
-                            x: place_x,
-                            y: place_y,
-                        },
-                    ))
-                    .id(),
-            )
-        }
-    }
-    
-
-    commands
-        .spawn((
-            // SpriteSheetBundle {
-            //     texture_atlas: texture_atlas_handle,
-            //     transform: Transform::from_xyz(10., 10., 0.),
-            //     ..Default::default()
-            // },
-            Collider::ball(15.),
-            SpatialBundle {
-                transform: Transform::from_xyz(10., 10., 0.0),
-                ..Default::default()
-            },
-            Velocity {
-                angvel: 0.0,
-                linvel: Vec2 { x: 50.0, y: 80.0 },
-            },
-            Damping {
-                linear_damping: 1.0,
-                angular_damping: 50.5,
-            },
-            RigidBody::Dynamic,
-            AdditionalMassProperties::Mass(1.),
-            ExternalImpulse {
-                impulse: Vec2::ZERO,
-                torque_impulse: 0.0,
-            },
-            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-            // Sensor,
-            ActiveEvents::COLLISION_EVENTS,
-            GravityScale(0.),
-            Asteroid,
-            EnemyHelth { health: 3 },
-        )).insert(LockedAxes::ROTATION_LOCKED)
-        .push_children(&astroid_copies);
-}
-
-fn animate_sprite(
-    time: Res<Time>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut query: Query<(
-        &mut AnimationTimer,
-        &mut TextureAtlasSprite,
-        &Handle<TextureAtlas>,
-    )>,
-) {
-    for (mut timer, mut sprite, texture_atlas_handle) in &mut query {
-        timer.tick(time.delta());
-        if timer.just_finished() {
-            let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-            sprite.index = (sprite.index + 1) % texture_atlas.textures.len();
-        }
+    let Some(fragments) = archetypes
+        .get(&enemy.archetype)
+        .and_then(|archetype| archetype.fragments.as_ref())
+    else {
+        return;
+    };
+    let Some(fragment) = archetypes.get(&fragments.archetype) else {
+        return;
+    };
+    let position = transform.translation.truncate();
+    let linvel = velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel);
+    let count = rng.asteroids.gen_range(fragments.min..=fragments.max);
+    let first_angle = rng.asteroids.gen_range(0.0..TAU);
+    for i in 0..count {
+        let direction = Vec2::from_angle(first_angle + TAU * i as f32 / count as f32);
+        archetypes.spawn(
+            commands,
+            &mut rng.asteroids,
+            &fragments.archetype,
+            position + direction * fragment.radius * 1.2,
+            linvel + direction * FRAGMENT_SPREAD_SPEED,
+        );
     }
 }
 
-fn check_shooted(
+pub(crate) fn check_shooted(
     mut commands: Commands,
     mut collisions: EventReader<CollisionEvent>,
-    shots: Query<(&mut Shot, Entity)>,
-    mut asteroids: Query<(&mut Asteroid, Entity, Option<&mut EnemyHelth>, Option<&TowerField>)>,
+    mut asteroids: Query<(
+        &mut Asteroid,
+        Entity,
+        Option<&mut EnemyHelth>,
+        Option<&TowerField>,
+        &Transform,
+        Option<&Velocity>,
+        Option<&Enemy>,
+        Option<(&TextureAtlasSprite, &Handle<TextureAtlas>, &Animation)>,
+        Option<&Boss>,
+    )>,
     player: Query<(&mut Player, Entity)>,
+    seams: Query<(), With<SeamCollider>>,
+    mut shots: Query<(&Velocity, &mut PlayerShot), Without<Asteroid>>,
     mut board_size: ResMut<BoardSize>,
-    mut game_state: ResMut<CurrentGame>,
+    mut game_state: ResMut<State<GameState>>,
+    archetypes: Res<Archetypes>,
+    mut rng: ResMut<GameRng>,
+    mut destroyed: EventWriter<EnemyDestroyed>,
+    mut hits: EventWriter<EnemyHit>,
 ) {
-    // honestly please don't bother making this better - don't waste your time
-    let asteroid_entities: Vec<Entity> = asteroids.iter().map(|entry| entry.1).collect();
-    let shot_entities: Vec<Entity> = shots.iter().map(|entry| entry.1).collect();
-
-    for collision in collisions.iter() {
-        if let CollisionEvent::Started(a, b, _info) = collision {
-            let player_id = player.single().1.index();
-
-            if player_id == a.index() || player_id == b.index() {
-                if player_id == a.index() {
-                    if asteroid_entities.contains(b) {
-                        game_state.state = GameState::Died
-                    }
-                } else {
-                    if asteroid_entities.contains(a) {
-                        game_state.state = GameState::Died
-                    }
-                }
-                continue;
-            }
-
-            if asteroids.contains(*a) && asteroids.contains(*b) {
-                continue;
+    let Ok((_, player)) = player.get_single() else {
+        return;
+    };
+    for (a, b) in owner_collisions(&mut collisions, &seams) {
+        // Left to `hull::damage_player`.
+        if a == player || b == player {
+            continue;
+        }
+        let Some((enemy, other)) = enemy_first(|entity| asteroids.contains(entity), a, b) else {
+            continue;
+        };
+        let Ok(asteroid) = asteroids.get_mut(enemy) else {
+            continue;
+        };
+        let Some(mut health) = asteroid.2 else {
+            continue;
+        };
+
+        // Only the player's shots hurt enemies, anything else that touches
+        // them, like a pickup, is left alone.
+        let Ok((velocity, mut shot)) = shots.get_mut(other) else {
+            continue;
+        };
+        hits.send(EnemyHit {
+            enemy,
+            velocity: velocity.linvel,
+        });
+        // Already destroyed by another hit this frame.
+        let was_alive = health.health > 0;
+        health.health = health.health.saturating_sub(shot.damage);
+        if health.health == 0 {
+            if let Some(tower_field) = asteroid.3 {
+                board_size.size += tower_field.timer.elapsed().as_secs_f32();
             }
-
-
-            if let Ok(mut asteroid) = asteroids.get_mut(*a) {
-                if let Some(mut health) = asteroid.2 {
-                    health.health -= 1;
-                    if health.health <= 0 {
-                        if let Some(tower_field) = asteroid.3 {
-                            board_size.size += tower_field.timer.elapsed().as_secs_f32();
-                        }
-                        commands.entity(*a).despawn_recursive();
-                    }
-                    commands.entity(*b).despawn_recursive();
-                    continue;
+            if was_alive {
+                destroyed.send(EnemyDestroyed {
+                    archetype: asteroid
+                        .6
+                        .map_or_else(String::new, |enemy| enemy.archetype.clone()),
+                    position: asteroid.4.translation.truncate(),
+                });
+                if let Some((sprite, atlas, animation)) = &asteroid.7 {
+                    animation::spawn_dying(&mut commands, sprite, atlas, asteroid.4, animation);
                 }
-            }
-
-            if let Ok(mut asteroid) = asteroids.get_mut(*b) {
-                if let Some(mut health) = asteroid.2 {
-                    health.health -= 1;
-                    if health.health <= 0 {
-                        if let Some(tower_field) = asteroid.3 {
-                            board_size.size += tower_field.timer.elapsed().as_secs_f32();
-                        }
-                        commands.entity(*b).despawn_recursive();
-                    }
-                    commands.entity(*a).despawn_recursive();
-                    continue;
+                if let Some(enemy) = asteroid.6 {
+                    split_enemy(
+                        &mut commands,
+                        &archetypes,
+                        &mut rng,
+                        enemy,
+                        asteroid.4,
+                        asteroid.5,
+                    );
+                }
+                if asteroid.8.is_some() {
+                    let _ = game_state.set(GameState::Won);
                 }
             }
-
-            // commands.entity(*a).despawn_recursive();
-            // commands.entity(*b).despawn_recursive();
+            commands.entity(enemy).despawn_recursive();
         }
-    }
-}
-
-fn correct_child_on_size_change(
-    mut q_child: Query<(&Parent, &mut Transform, &SpriteClone)>,
-    q_parent: Query<&GlobalTransform>,
-    board_size: Res<BoardSize>,
-) {
-    if !board_size.is_changed() {
-        return;
-    }
-
-    for (_parent, mut child_transform, location) in q_child.iter_mut() {
-        let updated_x = 4.0 * board_size.size - (location.x as f32 * board_size.size);
-        let updated_y = 2.0 * board_size.size - (location.y as f32 * board_size.size);
-
-        child_transform.translation.x = updated_x;
-        child_transform.translation.y = updated_y;
-    }
-}
-
-fn target_move_player(
-    mut commands: Commands,
-    player: Query<(&Transform, With<Player>)>,
-    board_size: Res<BoardSize>,
-    mut asteroids: Query<(
-        &mut Velocity,
-        &Transform,
-        &mut ExternalImpulse,
-        With<Asteroid>,
-    )>,
-) {
-    let player_pos = player.single().0.translation;
-
-    for (mut asteroid_vel, asteroid_trans, mut asteroid_impulse, _) in asteroids.iter_mut() {
-        let x_target =
-            find_impulse_direction(board_size.size, asteroid_trans.translation.x, player_pos.x);
-        let y_target =
-            find_impulse_direction(board_size.size, asteroid_trans.translation.y, player_pos.y);
-
-        asteroid_impulse.impulse = Vec2 {
-            x: x_target,
-            y: y_target,
+        if !shot.pierce() {
+            commands.entity(other).despawn_recursive();
         }
-        .normalize()
-            * 10.0;
     }
 }
 
-fn find_impulse_direction(board_size: f32, position: f32, target: f32) -> f32 {
-    let distance = target - position;
-    if distance.abs() < board_size / 2.0 {
-        return distance;
-    }
-    let mirrowed_travel = board_size - distance.abs();
-    if distance < 0.0 {
-        return mirrowed_travel;
+/// The enemy of a colliding pair first, `None` unless exactly one of them
+/// is an enemy.
+fn enemy_first(
+    is_enemy: impl Fn(Entity) -> bool,
+    a: Entity,
+    b: Entity,
+) -> Option<(Entity, Entity)> {
+    match (is_enemy(a), is_enemy(b)) {
+        (true, false) => Some((a, b)),
+        (false, true) => Some((b, a)),
+        _ => None,
     }
-    mirrowed_travel.neg()
 }
@@ -1,69 +1,214 @@
+use std::f32::consts::TAU;
 use std::time::Duration;
 
-use crate::spawn_asteroids::Asteroid;
-use crate::spawn_asteroids::Factory;
-use crate::Score;
+use crate::archetypes::Archetypes;
+use crate::director::Director;
+use crate::hull::ContactDamage;
+use crate::pickups::PowerUps;
+use crate::rng::GameRng;
+use crate::torus;
+use crate::wrap_ghost::{SpriteCopy, WrapCollider, WrapGhost};
+use crate::{GameState, GameStep};
+use crate::{Player, Score, FULL_BOARD};
 
 use super::BoardSize;
 use super::DropAfter;
-use super::Player;
-use super::SpriteClone;
-use super::SpriteCopy;
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
-use bevy_rapier2d::parry::shape::Ball;
 use bevy_rapier2d::prelude::*;
 use rand::prelude::*;
+use serde::Deserialize;
 
 pub struct SpawnTimesDoublers;
 
 impl Plugin for SpawnTimesDoublers {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(test_spawn_towers)
-            .insert_resource(TowerQueue { queue: vec![] })
-            .add_system(spawn_and_destroy_factories)
-            .add_system(flicker)
-            .add_system(fix_projectile_size)
-            .add_system(spawn_projectile)
-            .add_system(spawn_after_finished_queue)
-            // .add_system(correct_position_on_change)
-            .add_system(time_and_adjust_board);
+        app.insert_resource(TowerQueue { queue: vec![] })
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(flicker.label(GameStep::Board).after(GameStep::Collide))
+                    .with_system(
+                        fix_projectile_size
+                            .label(GameStep::Board)
+                            .after(GameStep::Collide),
+                    )
+                    .with_system(
+                        spawn_and_destroy_factories
+                            .label(GameStep::Spawn)
+                            .after(GameStep::Input),
+                    )
+                    .with_system(
+                        spawn_after_finished_queue
+                            .label(GameStep::Spawn)
+                            .after(spawn_and_destroy_factories),
+                    )
+                    .with_system(
+                        spawn_projectile
+                            .label(GameStep::Spawn)
+                            .after(spawn_after_finished_queue),
+                    )
+                    .with_system(
+                        time_and_adjust_board
+                            .label(GameStep::Board)
+                            .after(crate::update_time),
+                    ),
+            );
     }
 }
 
+const PROJECTILE_SPEED: f32 = 300.0;
+
+/// How long shots across the seam take to reach the player, they are fast
+/// enough to get there before they drop.
+const SEAM_FLIGHT_TIME: f32 = 1.5;
+
 #[derive(Component)]
 pub struct TowerField {
     pub timer: Timer,
+    /// Board shrink per second of `timer`.
+    pub shrink: f32,
+}
+
+impl TowerField {
+    pub fn new(shrink: f32) -> Self {
+        TowerField {
+            timer: Timer::new(Duration::from_secs(500), TimerMode::Once),
+            shrink,
+        }
+    }
 }
 #[derive(Component)]
 struct Flicker(Timer);
 
 #[derive(Component)]
-struct Projectile;
+pub(crate) struct Projectile;
+
+/// How a tower shoots.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum FiringPattern {
+    /// One shot at where the player is headed.
+    Aimed,
+    /// `count` shots evenly around the tower, from a random start.
+    Radial { count: u32 },
+    /// `arms` shots evenly around the tower, turning by `step` radians
+    /// every volley.
+    Spiral { arms: u32, step: f32 },
+    /// `count` shots at the player's images on the neighbouring tiles, so
+    /// they come at it from behind across the edges and arrive together.
+    AcrossSeam { count: u32 },
+}
 
-#[derive(Component, Clone)]
+impl FiringPattern {
+    /// Seconds between volleys, the more it fires at once the longer it
+    /// waits.
+    fn interval(self) -> f32 {
+        match self {
+            FiringPattern::Aimed => 2.5,
+            FiringPattern::Radial { .. } => 5.0,
+            FiringPattern::Spiral { .. } => 0.6,
+            FiringPattern::AcrossSeam { .. } => 2.0,
+        }
+    }
+}
+
+/// Shoots projectiles in its [`FiringPattern`].
+#[derive(Component)]
+pub struct Firing {
+    pattern: FiringPattern,
+    timer: Timer,
+    /// Where the next spiral volley starts.
+    spiral_angle: f32,
+    /// Of each projectile.
+    damage: f32,
+}
+
+#[derive(Clone)]
 pub struct TowerTimer {
     timer: Timer,
     factory_timer: Timer,
-    projectile_timer: Timer,
     xpos: f32,
     ypos: f32,
+    /// Archetype to spawn.
+    enemy: String,
 }
 
 impl TowerTimer {
-    pub fn new(board_size: &f32) -> Self {
-        let mut board_size = board_size.clone();
+    /// An `enemy` that shows up after `delay` somewhere on the board,
+    /// telegraphed for the last two seconds of it.
+    pub fn new(enemy: &str, delay: f32, board_size: &f32, rng: &mut impl Rng) -> Self {
+        let mut board_size = *board_size;
         if board_size < 0.0 {
             board_size = 110.0
         }
-        let mut rng = rand::thread_rng();
-        let range = (-board_size / 2.0) + 50.0..(board_size / 2.0) - 50.0;
+        let half = (board_size / 2.0 - 50.0).max(1.0);
+        let range = -half..half;
         TowerTimer {
-            timer: Timer::from_seconds(5.0, TimerMode::Once),
-            factory_timer: Timer::from_seconds(3.0, TimerMode::Once),
-            projectile_timer: Timer::from_seconds(5.0, TimerMode::Repeating),
+            timer: Timer::from_seconds(delay, TimerMode::Once),
+            factory_timer: Timer::from_seconds((delay - 2.0).max(0.0), TimerMode::Once),
             xpos: rng.gen_range(range.clone()),
-            ypos: rng.gen_range(range.clone()),
+            ypos: rng.gen_range(range),
+            enemy: enemy.to_string(),
+        }
+    }
+}
+
+impl Firing {
+    pub fn new(pattern: FiringPattern, damage: f32, rng: &mut impl Rng) -> Self {
+        Firing {
+            pattern,
+            timer: Timer::from_seconds(pattern.interval(), TimerMode::Repeating),
+            spiral_angle: rng.gen_range(0.0..TAU),
+            damage,
+        }
+    }
+
+    /// Carries on firing in `pattern` instead.
+    pub fn switch(&mut self, pattern: FiringPattern) {
+        if self.pattern != pattern {
+            self.pattern = pattern;
+            self.timer = Timer::from_seconds(pattern.interval(), TimerMode::Repeating);
+        }
+    }
+
+    /// Velocities of the next volley from `position`.
+    fn volley(
+        &mut self,
+        position: Vec2,
+        player: Option<(Vec2, Vec2)>,
+        board_size: f32,
+        rng: &mut impl Rng,
+    ) -> Vec<Vec2> {
+        let around = |count: u32, start: f32| {
+            (0..count)
+                .map(|i| Vec2::from_angle(start + TAU * i as f32 / count as f32) * PROJECTILE_SPEED)
+                .collect()
+        };
+        match self.pattern {
+            FiringPattern::Aimed => player
+                .map(|(target, velocity)| {
+                    torus::intercept_direction(
+                        position,
+                        PROJECTILE_SPEED,
+                        target,
+                        velocity,
+                        board_size,
+                    ) * PROJECTILE_SPEED
+                })
+                .filter(|velocity| *velocity != Vec2::ZERO)
+                .into_iter()
+                .collect(),
+            FiringPattern::Radial { count } => around(count, rng.gen_range(0.0..TAU)),
+            FiringPattern::Spiral { arms, step } => {
+                let start = self.spiral_angle;
+                self.spiral_angle = (start + step) % TAU;
+                around(arms, start)
+            }
+            FiringPattern::AcrossSeam { count } => player
+                .map(|(target, _)| torus::seam_deltas(position, target, board_size))
+                .into_iter()
+                .flatten()
+                .take(count as usize)
+                .map(|delta| delta / SEAM_FLIGHT_TIME)
+                .collect(),
         }
     }
 }
@@ -78,110 +223,68 @@ pub struct EnemyHelth {
     pub health: u8,
 }
 
-#[derive(Component)]
-pub struct TowerCircle;
-
-fn fix_projectile_size(mut projectiles: Query<(&DropAfter, Option<&mut Collider>, Option<&mut Sprite>), With<Projectile>>) {
-    for mut projectile in projectiles.iter_mut() {
-        let size_perc = projectile.0.time / 2.0;
+fn fix_projectile_size(
+    mut projectiles: Query<
+        (&DropAfter, Option<&mut Collider>, Option<&mut Sprite>),
+        With<Projectile>,
+    >,
+) {
+    for projectile in projectiles.iter_mut() {
+        let size_perc = projectile.0.time.max(0.1) / 2.0;
         if let Some(mut collider) = projectile.1 {
             *collider = Collider::ball(15.0 * size_perc);
         }
         if let Some(mut sprite) = projectile.2 {
-            sprite.custom_size = Some(Vec2 { x: 20.0, y: 20.0 }*size_perc);
+            sprite.custom_size = Some(Vec2 { x: 20.0, y: 20.0 } * size_perc);
         }
     }
 }
 
-fn spawn_projectile(
+pub(crate) fn spawn_projectile(
     mut commands: Commands,
-    mut tower_timers: Query<&mut TowerTimer>,
+    mut towers: Query<(&mut Firing, &Transform)>,
+    player: Query<(&Transform, &Velocity), With<Player>>,
+    board_size: Res<BoardSize>,
     time: Res<Time>,
     asset_server: Res<AssetServer>,
-    board_size: Res<BoardSize>,
+    director: Res<Director>,
+    mut rng: ResMut<GameRng>,
 ) {
-    for mut t_timer in tower_timers.iter_mut() {
-        if t_timer.projectile_timer.tick(time.delta()).just_finished() {
-            let mut rng = rand::thread_rng();
-            let mut xvel = rng.gen_range(-1..1);
-            let yvel = rng.gen_range(-1..1);
-            if (xvel, yvel) == (0, 0) {
-                xvel = 1;
-            }
-
-            let vel = Vec2 {
-                x: xvel as f32,
-                y: yvel as f32,
-            }
-            .normalize()
-                * 300.0;
-
-            let rows = 10;
-            let column = 5;
-            let offset_rows = board_size.size * ((rows - 1) / 2) as f32; // 2*size - placex * size
-            let offset_column = board_size.size * ((column - 1) / 2) as f32; // size
-
-            let mut tower_copies: Vec<Entity> = vec![];
-            for place_x in 0..rows {
-                for place_y in 0..column {
-                    if (place_x, place_y) == (0, 0) {
-                        continue;
-                    }
-                    let tower = commands
-                        .spawn((
-                            DropAfter { time: 2.0 },
-                            Asteroid,
-                            Projectile,
-                            SpriteBundle {
-                                texture: asset_server.load("tower.png"),
-                                transform: Transform::from_xyz(
-                                    offset_rows - (place_x as f32 * board_size.size),
-                                    offset_column - (place_y as f32 * board_size.size),
-                                    0.,
-                                ),
-                                ..Default::default()
-                            },
-                            SpriteCopy,
-                            SpriteClone {
-                                x: place_x,
-                                y: place_y,
-                            },
-                        ))
-                        .id();
-                    tower_copies.push(tower);
-                }
-            }
-
-            commands
-                .spawn((
+    let delta = time.delta().mul_f32(director.aggression());
+    let player = player
+        .get_single()
+        .ok()
+        .map(|(transform, velocity)| (transform.translation.truncate(), velocity.linvel));
+    for (mut firing, transform) in towers.iter_mut() {
+        if firing.timer.tick(delta).just_finished() {
+            let position = transform.translation.truncate();
+            let volley = firing.volley(position, player, board_size.size, &mut rng.projectiles);
+            for linvel in volley {
+                commands.spawn((
                     DropAfter { time: 2.0 },
-                    Asteroid,
                     Projectile,
+                    ContactDamage(firing.damage),
                     ActiveEvents::COLLISION_EVENTS,
                     Collider::ball(15.0),
                     RigidBody::KinematicVelocityBased,
                     LockedAxes::ROTATION_LOCKED,
                     Velocity {
-                        linvel: vel,
+                        linvel,
                         ..default()
                     },
-                    SpatialBundle {
-                        transform: Transform::from_xyz(t_timer.xpos, t_timer.ypos, 0.),
+                    SpriteBundle {
+                        texture: asset_server.load("tower.png"),
+                        transform: Transform::from_translation(position.extend(0.)),
                         ..default()
                     },
-                    TowerField {
-                        timer: Timer::new(Duration::from_secs(500), TimerMode::Once),
-                    },
-                ))
-                .push_children(&tower_copies);
+                    WrapGhost,
+                    WrapCollider,
+                ));
+            }
         }
     }
 }
 
-fn test_spawn_towers(mut tower_queue: ResMut<TowerQueue>, board_size: Res<BoardSize>) {
-    // tower_queue.queue.push(TowerTimer::new(&board_size.size));
-}
-
 fn flicker(mut flickerers: Query<(&mut Flicker, &mut Visibility)>, time: Res<Time>) {
     for mut flickerer in flickerers.iter_mut() {
         if flickerer.0 .0.tick(time.delta()).just_finished() {
@@ -193,10 +296,8 @@ fn flicker(mut flickerers: Query<(&mut Flicker, &mut Visibility)>, time: Res<Tim
 fn spawn_and_destroy_factories(
     mut commands: Commands,
     mut tower_queue: ResMut<TowerQueue>,
-    mut factories: Query<Entity, With<TowerTimer>>,
     time: Res<Time>,
-    board_size: Res<BoardSize>,
-    asset_server: Res<AssetServer>,
+    archetypes: Res<Archetypes>,
 ) {
     for tower_factory in tower_queue.queue.iter_mut() {
         if tower_factory
@@ -204,55 +305,20 @@ fn spawn_and_destroy_factories(
             .tick(time.delta())
             .just_finished()
         {
-            let rows = 10;
-            let column = 5;
-            let offset_rows = board_size.size * ((rows - 1) / 2) as f32;
-            let offset_column = board_size.size * ((column - 1) / 2) as f32;
-
-            let mut tower_copies: Vec<Entity> = vec![];
-            for place_x in 0..rows {
-                for place_y in 0..column {
-                    if (place_x, place_y) == (0, 0) {
-                        continue;
-                    }
-                    let tower = commands
-                        .spawn((
-                            Flicker(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                            DropAfter { time: 2.0 },
-                            SpriteBundle {
-                                texture: asset_server.load("tower.png"),
-                                transform: Transform::from_xyz(
-                                    offset_rows - (place_x as f32 * board_size.size),
-                                    offset_column - (place_y as f32 * board_size.size),
-                                    0.,
-                                ),
-                                ..Default::default()
-                            },
-                            SpriteCopy,
-                            SpriteClone {
-                                x: place_x,
-                                y: place_y,
-                            },
-                        ))
-                        .id();
-                    tower_copies.push(tower);
-                }
-            }
-
-            commands
-                .spawn((
-                    DropAfter { time: 2.0 },
-                    Flicker(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                    SpriteBundle {
-                        texture: asset_server.load("tower.png"),
-                        transform: Transform::from_xyz(tower_factory.xpos, tower_factory.ypos, 0.),
-                        ..Default::default()
-                    },
-                    TowerField {
-                        timer: Timer::new(Duration::from_secs(500), TimerMode::Once),
-                    },
-                ))
-                .push_children(&tower_copies);
+            let (Some(archetype), Some(mut sprite)) = (
+                archetypes.get(&tower_factory.enemy),
+                archetypes.sprite(&tower_factory.enemy),
+            ) else {
+                continue;
+            };
+            sprite.transform = Transform::from_xyz(tower_factory.xpos, tower_factory.ypos, 0.);
+            commands.spawn((
+                DropAfter { time: 2.0 },
+                Flicker(Timer::from_seconds(0.1, TimerMode::Repeating)),
+                sprite,
+                TowerField::new(archetype.shrink),
+                WrapGhost,
+            ));
         }
     }
 }
@@ -261,22 +327,17 @@ fn spawn_after_finished_queue(
     time: Res<Time>,
     mut tower_queue: ResMut<TowerQueue>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    board_size: Res<BoardSize>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    archetypes: Res<Archetypes>,
+    mut rng: ResMut<GameRng>,
 ) {
     for (i, tower) in tower_queue.queue.iter_mut().enumerate() {
         if tower.timer.tick(time.delta()).finished() {
-            spawn_one_doubler(
-                commands,
-                asset_server,
-                board_size,
-                meshes,
-                materials,
-                tower.xpos,
-                tower.ypos,
-                tower.clone(),
+            archetypes.spawn(
+                &mut commands,
+                &mut rng.towers,
+                &tower.enemy,
+                Vec2::new(tower.xpos, tower.ypos),
+                Vec2::ZERO,
             );
             tower_queue.queue.swap_remove(i);
             break;
@@ -284,90 +345,21 @@ fn spawn_after_finished_queue(
     }
 }
 
-fn spawn_one_doubler(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    board_size: Res<BoardSize>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    xpos: f32,
-    ypos: f32,
-    tower_timer: TowerTimer,
-) {
-    let rows = 10;
-    let column = 5;
-    let offset_rows = board_size.size * ((rows - 1) / 2) as f32;
-    let offset_column = board_size.size * ((column - 1) / 2) as f32;
-    let mut rng = thread_rng();
-    let random_x_pos = xpos;
-    // (rng.gen_range(0..board_size.size as i32) - (board_size.size / 2.0) as i32) as f32;
-    let random_y_pos = xpos;
-    // (rng.gen_range(0..board_size.size as i32) - (board_size.size / 2.0) as i32) as f32;
-
-    let mut tower_copies: Vec<Entity> = vec![];
-
-    for place_x in 0..rows {
-        for place_y in 0..column {
-            let tower = commands
-                .spawn((
-                    Asteroid,
-                    SpriteBundle {
-                        texture: asset_server.load("tower.png"),
-                        transform: Transform::from_xyz(
-                            offset_rows - (place_x as f32 * board_size.size),
-                            offset_column - (place_y as f32 * board_size.size),
-                            0.,
-                        ),
-                        ..Default::default()
-                    },
-                    SpriteCopy,
-                    SpriteClone {
-                        x: place_x,
-                        y: place_y,
-                    },
-                    TowerField {
-                        timer: Timer::new(Duration::from_secs(5), TimerMode::Repeating),
-                    },
-                ))
-                .id();
-            tower_copies.push(tower);
-        }
-    }
-
-    commands
-        .spawn((
-            tower_timer,
-            Asteroid,
-            AdditionalMassProperties::Mass(0.0),
-            ActiveEvents::COLLISION_EVENTS,
-            Collider::ball(15.),
-            EnemyHelth { health: 10 },
-            SpriteBundle {
-                texture: asset_server.load("tower.png"),
-                transform: Transform::from_xyz(xpos, ypos, 0.),
-                ..Default::default()
-            },
-            TowerField {
-                timer: Timer::new(Duration::from_secs(500), TimerMode::Once),
-            },
-        ))
-        .push_children(&tower_copies);
-}
-
-fn time_and_adjust_board(
+pub(crate) fn time_and_adjust_board(
     mut timers: Query<(&mut TowerField, Without<SpriteCopy>, Without<Projectile>)>,
     time: Res<Time>,
     mut board_size: ResMut<BoardSize>,
     mut score: ResMut<Score>,
+    power_ups: Query<&PowerUps>,
 ) {
     let change_threshold = 1.0;
-    let original_size = 800.0;
+    let original_size = FULL_BOARD + power_ups.iter().map(PowerUps::board_bonus).sum::<f32>();
     let mut sum_tower_size = 0.0;
 
     for mut timer in timers.iter_mut() {
         timer.0.timer.tick(time.delta());
         score.score += time.delta().as_secs_f32() / 2.0;
-        sum_tower_size += timer.0.timer.elapsed().as_secs_f32()*1.5;
+        sum_tower_size += timer.0.timer.elapsed().as_secs_f32() * timer.0.shrink;
     }
 
     let next_size: f32 = original_size - sum_tower_size;
@@ -378,22 +370,3 @@ fn time_and_adjust_board(
         board_size.size = next_size;
     }
 }
-
-fn correct_position_on_change(
-    mut towers: Query<(
-        &mut Transform,
-        &SpriteClone,
-        Or<(&TowerField, &TowerCircle)>,
-    )>,
-    board_size: Res<BoardSize>,
-) {
-    if board_size.is_changed() {
-        for (mut transform, location, _) in towers.iter_mut() {
-            let updated_x = 2.0 * board_size.size - (location.x as f32 * board_size.size);
-            let updated_y = board_size.size - (location.y as f32 * board_size.size);
-
-            transform.translation.x = updated_x;
-            transform.translation.y = updated_y;
-        }
-    }
-}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::director::Director;
+use crate::torus;
+use crate::wrap_ghost::SpriteCopy;
+use crate::{BoardSize, GameState, GameStep, Player};
+
+/// Chases the player as part of a swarm. Each behaviour gives a direction,
+/// the weighted sum of them is pushed with up to `impulse` per frame.
+#[derive(Component, Deserialize, Clone)]
+pub struct Steering {
+    pub impulse: f32,
+    /// Head for the player.
+    pub seek: f32,
+    /// Keep out of the way of neighbours closer than `separation_radius`.
+    pub separation: f32,
+    pub separation_radius: f32,
+    /// Fly along with neighbours closer than `alignment_radius`.
+    pub alignment: f32,
+    pub alignment_radius: f32,
+    /// Swerve around [`Obstacle`]s up to `look_ahead` in front.
+    pub avoidance: f32,
+    pub look_ahead: f32,
+    /// Own size, for clearing obstacles.
+    #[serde(skip)]
+    pub radius: f32,
+}
+
+/// Something steering enemies fly around instead of into.
+#[derive(Component)]
+pub struct Obstacle {
+    pub radius: f32,
+}
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Running)
+                .with_system(steer.label(GameStep::Steer).after(GameStep::Spawn)),
+        );
+    }
+}
+
+fn steer(
+    player: Query<&Transform, With<Player>>,
+    board_size: Res<BoardSize>,
+    mut agents: Query<(
+        Entity,
+        &Steering,
+        &Transform,
+        &Velocity,
+        &mut ExternalImpulse,
+    )>,
+    obstacles: Query<(&Obstacle, &Transform), Without<SpriteCopy>>,
+    director: Res<Director>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let player = player.translation.truncate();
+    let size = board_size.size;
+    let flock: Vec<(Entity, Vec2, Vec2)> = agents
+        .iter()
+        .map(|(entity, _, transform, velocity, _)| {
+            (entity, transform.translation.truncate(), velocity.linvel)
+        })
+        .collect();
+
+    for (entity, steering, transform, velocity, mut impulse) in agents.iter_mut() {
+        let position = transform.translation.truncate();
+        let seek = torus::wrapped_delta(position, player, size).normalize_or_zero();
+
+        let mut separation = Vec2::ZERO;
+        let mut heading = Vec2::ZERO;
+        for &(other, other_position, other_velocity) in &flock {
+            if other == entity {
+                continue;
+            }
+            let delta = torus::wrapped_delta(position, other_position, size);
+            let distance = delta.length();
+            if distance < steering.separation_radius && distance > 0.0 {
+                // Stronger the closer they are.
+                separation -= delta / distance * (1.0 - distance / steering.separation_radius);
+            }
+            if distance < steering.alignment_radius {
+                heading += other_velocity;
+            }
+        }
+        let alignment = heading.normalize_or_zero();
+
+        let mut avoidance = Vec2::ZERO;
+        for (obstacle, obstacle_transform) in obstacles.iter() {
+            let obstacle_position = obstacle_transform.translation.truncate();
+            let clearance = obstacle.radius + steering.radius;
+            if torus::wrapped_raycast(
+                position,
+                velocity.linvel,
+                steering.look_ahead,
+                obstacle_position,
+                clearance,
+                size,
+            )
+            .is_none()
+            {
+                continue;
+            }
+            // Sideways, away from the obstacle's centre.
+            let to_obstacle = torus::wrapped_delta(position, obstacle_position, size);
+            let forward = velocity.linvel.normalize_or_zero();
+            let side = to_obstacle - forward * to_obstacle.dot(forward);
+            avoidance -= side.try_normalize().unwrap_or(forward.perp());
+        }
+
+        let direction = seek * steering.seek
+            + separation * steering.separation
+            + alignment * steering.alignment
+            + avoidance * steering.avoidance;
+        impulse.impulse =
+            direction.clamp_length_max(1.0) * steering.impulse * director.aggression();
+    }
+}
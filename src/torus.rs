@@ -0,0 +1,353 @@
+//! Geometry on the wrapping board. The board is a square of side `board_size`
+//! centered on the origin whose opposite edges are glued together, so every
+//! point has an image on each board-sized tile and the shortest way between
+//! two points may cross an edge.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Boards smaller than this are treated as flat, the math below divides by
+/// the size.
+const MIN_BOARD_SIZE: f32 = 1e-3;
+
+/// Spots picked by [`furthest_spot`] stay this far inside the edges.
+const SPOT_MARGIN: f32 = 50.0;
+
+/// Shortest displacement from `from` to `to`, possibly across an edge. Each
+/// component lies in `[-board_size / 2, board_size / 2]`.
+pub fn wrapped_delta(from: Vec2, to: Vec2, board_size: f32) -> Vec2 {
+    let delta = to - from;
+    if board_size.abs() < MIN_BOARD_SIZE {
+        return delta;
+    }
+    delta - (delta / board_size).round() * board_size
+}
+
+/// Length of [`wrapped_delta`].
+pub fn wrapped_distance(a: Vec2, b: Vec2, board_size: f32) -> f32 {
+    wrapped_delta(a, b, board_size).length()
+}
+
+/// The image of `position` on the central tile, in `[-board_size / 2,
+/// board_size / 2)`.
+pub fn wrap_position(position: Vec2, board_size: f32) -> Vec2 {
+    if board_size.abs() < MIN_BOARD_SIZE {
+        return position;
+    }
+    let half = board_size / 2.0;
+    Vec2::new(
+        (position.x + half).rem_euclid(board_size) - half,
+        (position.y + half).rem_euclid(board_size) - half,
+    )
+}
+
+/// The image of `target` closest to `from`. Unlike `target` itself it may lie
+/// off the central tile, which is what aiming and steering want.
+pub fn nearest_image(from: Vec2, target: Vec2, board_size: f32) -> Vec2 {
+    from + wrapped_delta(from, target, board_size)
+}
+
+/// Distance along `direction` from `origin` at which a ray first touches a
+/// circle of `radius` around `target`, following the ray across edges for
+/// at most `max_distance`.
+pub fn wrapped_raycast(
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+    target: Vec2,
+    radius: f32,
+    board_size: f32,
+) -> Option<f32> {
+    let direction = direction.try_normalize()?;
+    let size = board_size.abs();
+    if size < MIN_BOARD_SIZE {
+        return ray_circle(origin, direction, max_distance, target, radius);
+    }
+
+    // Every image the ray can reach lies within the box the ray sweeps,
+    // grown by the radius.
+    let target = nearest_image(origin, target, size);
+    let end = origin + direction * max_distance;
+    let first = ((origin.min(end) - radius - target) / size).floor();
+    let last = ((origin.max(end) + radius - target) / size).ceil();
+
+    let mut hit: Option<f32> = None;
+    for x in first.x as i32..=last.x as i32 {
+        for y in first.y as i32..=last.y as i32 {
+            let image = target + Vec2::new(x as f32, y as f32) * size;
+            if let Some(distance) = ray_circle(origin, direction, max_distance, image, radius) {
+                hit = Some(hit.map_or(distance, |hit| hit.min(distance)));
+            }
+        }
+    }
+    hit
+}
+
+/// Displacements from `from` to the images of `to` around the nearest one,
+/// each reached by crossing an edge the long way. Shortest first.
+pub fn seam_deltas(from: Vec2, to: Vec2, board_size: f32) -> Vec<Vec2> {
+    let nearest = wrapped_delta(from, to, board_size);
+    let mut deltas: Vec<Vec2> = [-1.0, 0.0, 1.0]
+        .into_iter()
+        .flat_map(|x| [-1.0, 0.0, 1.0].map(|y| Vec2::new(x, y)))
+        .filter(|tile| *tile != Vec2::ZERO)
+        .map(|tile| nearest + tile * board_size)
+        .collect();
+    deltas.sort_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+    deltas
+}
+
+/// Direction to shoot from `origin` at `speed` to meet a target that keeps
+/// moving at `target_velocity`, going the short way across edges. Aims
+/// straight at the target when it can't be caught.
+pub fn intercept_direction(
+    origin: Vec2,
+    speed: f32,
+    target: Vec2,
+    target_velocity: Vec2,
+    board_size: f32,
+) -> Vec2 {
+    let delta = wrapped_delta(origin, target, board_size);
+    // |delta + target_velocity * t| = speed * t, solved for the first t > 0.
+    let a = target_velocity.length_squared() - speed * speed;
+    let b = 2.0 * delta.dot(target_velocity);
+    let c = delta.length_squared();
+    let time = if a.abs() < f32::EPSILON {
+        (b < 0.0).then(|| -c / b)
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        (discriminant >= 0.0)
+            .then(|| {
+                let root = discriminant.sqrt();
+                let (t1, t2) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+                [t1.min(t2), t1.max(t2)].into_iter().find(|t| *t > 0.0)
+            })
+            .flatten()
+    };
+    match time {
+        Some(time) => (delta + target_velocity * time).normalize_or_zero(),
+        None => delta.normalize_or_zero(),
+    }
+}
+
+/// Whichever of `candidates` random spots on the board is furthest from the
+/// closest of `avoid`.
+pub fn furthest_spot(
+    avoid: &[Vec2],
+    candidates: usize,
+    board_size: f32,
+    rng: &mut impl Rng,
+) -> Vec2 {
+    let half = (board_size / 2.0 - SPOT_MARGIN).max(1.0);
+    let closest = |spot: Vec2| {
+        avoid
+            .iter()
+            .map(|other| wrapped_distance(spot, *other, board_size))
+            .fold(f32::INFINITY, f32::min)
+    };
+    (0..candidates)
+        .map(|_| Vec2::new(rng.gen_range(-half..half), rng.gen_range(-half..half)))
+        .max_by(|a, b| closest(*a).total_cmp(&closest(*b)))
+        .unwrap_or_default()
+}
+
+/// Ray against a single circle, `direction` must be normalized.
+fn ray_circle(
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+    center: Vec2,
+    radius: f32,
+) -> Option<f32> {
+    let to_center = center - origin;
+    if to_center.length_squared() <= radius * radius {
+        return Some(0.0);
+    }
+    let along = to_center.dot(direction);
+    let miss_squared = to_center.length_squared() - along * along;
+    if along < 0.0 || miss_squared > radius * radius {
+        return None;
+    }
+    let distance = along - (radius * radius - miss_squared).sqrt();
+    (distance <= max_distance).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 800.0;
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        (a - b).length() < 1e-3
+    }
+
+    #[test]
+    fn delta_stays_inside_when_shorter() {
+        let delta = wrapped_delta(Vec2::new(-100., 50.), Vec2::new(100., -50.), SIZE);
+        assert!(close(delta, Vec2::new(200., -100.)));
+    }
+
+    #[test]
+    fn delta_crosses_the_edge_when_shorter() {
+        let delta = wrapped_delta(Vec2::new(390., -390.), Vec2::new(-390., 390.), SIZE);
+        assert!(close(delta, Vec2::new(20., -20.)));
+    }
+
+    #[test]
+    fn delta_ignores_which_image_is_given() {
+        let from = Vec2::new(10., 20.);
+        let to = Vec2::new(-300., 250.);
+        let delta = wrapped_delta(from, to, SIZE);
+        for image in [to + Vec2::X * SIZE, to - Vec2::Y * SIZE * 3.] {
+            assert!(close(wrapped_delta(from, image, SIZE), delta));
+        }
+    }
+
+    #[test]
+    fn delta_is_antisymmetric() {
+        let a = Vec2::new(350., -10.);
+        let b = Vec2::new(-380., 200.);
+        assert!(close(wrapped_delta(a, b, SIZE), -wrapped_delta(b, a, SIZE)));
+    }
+
+    #[test]
+    fn delta_on_a_flat_board() {
+        let delta = wrapped_delta(Vec2::new(390., 0.), Vec2::new(-390., 0.), 0.);
+        assert!(close(delta, Vec2::new(-780., 0.)));
+    }
+
+    #[test]
+    fn distance_across_the_corner() {
+        let distance = wrapped_distance(Vec2::splat(397.), Vec2::splat(-397.), SIZE);
+        assert!((distance - 6. * 2f32.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wrap_position_lands_on_the_central_tile() {
+        assert!(close(
+            wrap_position(Vec2::new(410., -20.), SIZE),
+            Vec2::new(-390., -20.)
+        ));
+        assert!(close(
+            wrap_position(Vec2::new(-1210., 2000.), SIZE),
+            Vec2::new(390., -400.)
+        ));
+        assert!(close(
+            wrap_position(Vec2::new(-400., 399.), SIZE),
+            Vec2::new(-400., 399.)
+        ));
+        assert!(close(
+            wrap_position(Vec2::new(400., 0.), SIZE),
+            Vec2::new(-400., 0.)
+        ));
+    }
+
+    #[test]
+    fn nearest_image_can_be_off_the_board() {
+        let image = nearest_image(Vec2::new(390., 0.), Vec2::new(-390., 10.), SIZE);
+        assert!(close(image, Vec2::new(410., 10.)));
+    }
+
+    #[test]
+    fn raycast_hits_straight_ahead() {
+        let hit = wrapped_raycast(Vec2::ZERO, Vec2::X, 300., Vec2::new(200., 0.), 15., SIZE);
+        assert!((hit.unwrap() - 185.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn raycast_hits_across_the_edge() {
+        let hit = wrapped_raycast(
+            Vec2::new(300., 0.),
+            Vec2::X,
+            300.,
+            Vec2::new(-300., 0.),
+            15.,
+            SIZE,
+        );
+        assert!((hit.unwrap() - 185.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn raycast_takes_the_first_image() {
+        // Behind the ray, but reached again after going once around.
+        let hit = wrapped_raycast(Vec2::ZERO, Vec2::X, 1000., Vec2::new(-100., 0.), 15., SIZE);
+        assert!((hit.unwrap() - 685.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn raycast_stops_at_max_distance() {
+        let hit = wrapped_raycast(Vec2::ZERO, Vec2::X, 100., Vec2::new(200., 0.), 15., SIZE);
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn raycast_misses_to_the_side() {
+        let hit = wrapped_raycast(Vec2::ZERO, Vec2::X, 1000., Vec2::new(200., 100.), 15., SIZE);
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn intercept_leads_a_moving_target() {
+        let direction = intercept_direction(
+            Vec2::ZERO,
+            300.,
+            Vec2::new(300., 0.),
+            Vec2::new(0., 100.),
+            SIZE,
+        );
+        // Meets it after t where (300, 100t) is 300t away.
+        let t = 300. / 8f32.sqrt() / 100.;
+        assert!(close(direction, Vec2::new(300., 100. * t).normalize()));
+    }
+
+    #[test]
+    fn intercept_across_the_edge() {
+        let direction = intercept_direction(
+            Vec2::new(390., 0.),
+            300.,
+            Vec2::new(-390., 0.),
+            Vec2::ZERO,
+            SIZE,
+        );
+        assert!(close(direction, Vec2::X));
+    }
+
+    #[test]
+    fn intercept_gives_up_on_fast_targets() {
+        let direction = intercept_direction(
+            Vec2::ZERO,
+            100.,
+            Vec2::new(0., 200.),
+            Vec2::new(0., 500.),
+            SIZE,
+        );
+        assert!(close(direction, Vec2::Y));
+    }
+
+    #[test]
+    fn seam_deltas_go_the_long_way() {
+        let deltas = seam_deltas(Vec2::ZERO, Vec2::new(100., 0.), SIZE);
+        assert_eq!(deltas.len(), 8);
+        assert!(close(deltas[0], Vec2::new(-700., 0.)));
+        assert!(
+            close(deltas[1], Vec2::new(100., -800.)) || close(deltas[1], Vec2::new(100., 800.))
+        );
+    }
+
+    #[test]
+    fn raycast_from_inside() {
+        let hit = wrapped_raycast(Vec2::ZERO, Vec2::Y, 10., Vec2::new(5., 5.), 15., SIZE);
+        assert_eq!(hit, Some(0.));
+    }
+
+    #[test]
+    fn furthest_spot_stays_away_and_inside() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let spot = furthest_spot(&[Vec2::ZERO], 64, SIZE, &mut rng);
+        assert!(spot.abs().max_element() <= SIZE / 2. - SPOT_MARGIN);
+        assert!(wrapped_distance(spot, Vec2::ZERO, SIZE) > SIZE / 4.);
+    }
+}
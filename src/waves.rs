@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::director::DirectorConfig;
+use crate::ron_file::load_ron;
+
+/// When enemies show up during a run, see `assets/waves.ron`.
+#[derive(Resource, Deserialize, Clone)]
+pub struct WaveSchedule {
+    pub asteroids: Track,
+    pub towers: Track,
+    pub boss: BossSpawn,
+    pub director: DirectorConfig,
+}
+
+/// The enemy that ends the run, shooting it down wins.
+#[derive(Deserialize, Clone)]
+pub struct BossSpawn {
+    /// Archetype from `assets/enemies.ron`.
+    pub enemy: String,
+    /// Seconds into the run.
+    pub at: f32,
+}
+
+/// Spawns of one kind of enemy.
+#[derive(Deserialize, Clone)]
+pub struct Track {
+    /// Archetype from `assets/enemies.ron`.
+    pub enemy: String,
+    /// Other archetypes the track may spawn instead, once the director is
+    /// hard enough on the player.
+    #[serde(default)]
+    pub mix: Vec<Mix>,
+    pub wave: Spawns,
+    pub single: Spawns,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Mix {
+    pub enemy: String,
+    /// Lowest [`Director`](crate::director::Director) difficulty it shows
+    /// up at.
+    pub difficulty: f32,
+}
+
+impl Track {
+    /// Archetype of the next spawn, any of those allowed at `difficulty`
+    /// with the same chance.
+    pub fn pick(&self, difficulty: f32, rng: &mut impl Rng) -> &str {
+        let allowed: Vec<&str> = std::iter::once(self.enemy.as_str())
+            .chain(
+                self.mix
+                    .iter()
+                    .filter(|mix| mix.difficulty <= difficulty)
+                    .map(|mix| mix.enemy.as_str()),
+            )
+            .collect();
+        allowed[rng.gen_range(0..allowed.len())]
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Spawns {
+    pub first: f32,
+    pub interval: Interval,
+    /// One enemy per entry, spawning this long after the timer runs out.
+    pub delays: Vec<f32>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Interval {
+    pub start: f32,
+    pub shrink: f32,
+    pub min: f32,
+}
+
+impl Interval {
+    /// Harder difficulties come around sooner, but never below `min`.
+    pub fn at(&self, iteration: u32, difficulty: f32) -> f32 {
+        ((self.start - self.shrink * iteration as f32) / difficulty).max(self.min)
+    }
+}
+
+impl WaveSchedule {
+    pub fn load() -> Self {
+        load_ron("waves.ron", include_str!("../assets/waves.ron"))
+    }
+}
+
+/// Progress through one [`Track`].
+pub struct TrackTimers {
+    wave: Timer,
+    single: Timer,
+    pub iteration: u32,
+}
+
+impl TrackTimers {
+    pub fn new(track: &Track) -> Self {
+        TrackTimers {
+            wave: Timer::from_seconds(track.wave.first, TimerMode::Once),
+            single: Timer::from_seconds(track.single.first, TimerMode::Once),
+            iteration: 0,
+        }
+    }
+
+    /// Delays of the enemies that are due to be queued.
+    pub fn tick(&mut self, track: &Track, delta: Duration, difficulty: f32) -> Vec<f32> {
+        let mut delays = vec![];
+        if self.single.tick(delta).just_finished() {
+            delays.extend(&track.single.delays);
+        }
+        if self.wave.tick(delta).just_finished() {
+            delays.extend(&track.wave.delays);
+            self.single = Timer::from_seconds(
+                track.single.interval.at(self.iteration, difficulty),
+                TimerMode::Once,
+            );
+            self.wave = Timer::from_seconds(
+                track.wave.interval.at(self.iteration, difficulty),
+                TimerMode::Once,
+            );
+            self.iteration += 1;
+        }
+        delays
+    }
+}
+
+/// Where the current run is in the [`WaveSchedule`].
+#[derive(Resource)]
+pub struct WaveQueuers {
+    pub asteroids: TrackTimers,
+    pub towers: TrackTimers,
+}
+
+impl WaveQueuers {
+    pub fn new(schedule: &WaveSchedule) -> Self {
+        WaveQueuers {
+            asteroids: TrackTimers::new(&schedule.asteroids),
+            towers: TrackTimers::new(&schedule.towers),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn track() -> Track {
+        let spawns = Spawns {
+            first: 0.0,
+            interval: Interval {
+                start: 10.0,
+                shrink: 1.0,
+                min: 2.0,
+            },
+            delays: vec![],
+        };
+        Track {
+            enemy: "asteroid".to_string(),
+            mix: vec![Mix {
+                enemy: "comet".to_string(),
+                difficulty: 2.0,
+            }],
+            wave: spawns.clone(),
+            single: spawns,
+        }
+    }
+
+    #[test]
+    fn interval_shrinks_with_iterations_and_difficulty() {
+        let interval = track().wave.interval;
+        assert_eq!(interval.at(0, 1.0), 10.0);
+        assert_eq!(interval.at(3, 1.0), 7.0);
+        assert_eq!(interval.at(3, 2.0), 3.5);
+    }
+
+    #[test]
+    fn interval_stops_at_min() {
+        let interval = track().wave.interval;
+        assert_eq!(interval.at(20, 1.0), 2.0);
+        assert_eq!(interval.at(0, 100.0), 2.0);
+    }
+
+    #[test]
+    fn pick_leaves_out_mixes_above_difficulty() {
+        let track = track();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!((0..100).all(|_| track.pick(1.0, &mut rng) == "asteroid"));
+    }
+
+    #[test]
+    fn pick_mixes_in_once_hard_enough() {
+        let track = track();
+        let mut rng = StdRng::seed_from_u64(1);
+        let picks: Vec<&str> = (0..100).map(|_| track.pick(2.0, &mut rng)).collect();
+        assert!(picks.contains(&"asteroid"));
+        assert!(picks.contains(&"comet"));
+    }
+}
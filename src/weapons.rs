@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::input::PlayerInput;
+use crate::pickups::PowerUps;
+use crate::rng::GameRng;
+use crate::ron_file::load_ron;
+use crate::wrap_ghost::WrapGhost;
+use crate::{DropAfter, GameState, GameStep, Player};
+
+/// How long a weapon takes to be ready after switching to it, unless its
+/// cooldown is shorter.
+const SWITCH_TIME: f32 = 0.2;
+
+/// One of the player's guns, see `assets/weapons.ron`.
+#[derive(Deserialize, Clone)]
+pub struct Weapon {
+    pub name: String,
+    pub image: String,
+    pub cooldown: f32,
+    #[serde(default = "one")]
+    pub burst: u32,
+    #[serde(default)]
+    pub burst_interval: f32,
+    #[serde(default = "one")]
+    pub pellets: u32,
+    #[serde(default)]
+    pub fan: f32,
+    pub spread: f32,
+    pub speed: f32,
+    #[serde(default = "one_second")]
+    pub lifetime: f32,
+    pub damage: u8,
+    #[serde(default)]
+    pub pierce: u8,
+    #[serde(default = "no_scale")]
+    pub scale: Vec2,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn one_second() -> f32 {
+    1.0
+}
+
+fn no_scale() -> Vec2 {
+    Vec2::ONE
+}
+
+/// Every [`Weapon`], in the order they are numbered.
+#[derive(Resource)]
+pub struct Weapons(pub Vec<Weapon>);
+
+impl Weapons {
+    /// The weapons, of which there has to be at least one, so a file
+    /// without any gets the built-in ones too.
+    pub fn load() -> Self {
+        let builtin = include_str!("../assets/weapons.ron");
+        let weapons: Vec<Weapon> = load_ron("weapons.ron", builtin);
+        if !weapons.is_empty() {
+            return Weapons(weapons);
+        }
+        warn!("no weapons in weapons.ron, using the built-in ones");
+        Weapons(ron::from_str(builtin).expect("bad built-in weapons.ron"))
+    }
+}
+
+/// The player's weapon and how soon it can fire again.
+#[derive(Component)]
+pub struct Gun {
+    /// Index into [`Weapons`].
+    pub weapon: usize,
+    /// Seconds until the trigger can be pulled again. Kept as a number
+    /// rather than a [`Timer`] so that holding the trigger fires at exactly
+    /// the weapon's rate.
+    cooldown: f32,
+    /// Volleys of the current burst still to fire.
+    burst_left: u32,
+    burst: Timer,
+}
+
+impl Gun {
+    pub fn new(index: usize, weapon: &Weapon) -> Self {
+        Gun {
+            weapon: index,
+            cooldown: weapon.cooldown.min(SWITCH_TIME),
+            burst_left: 0,
+            burst: Timer::from_seconds(weapon.burst_interval.max(0.001), TimerMode::Repeating),
+        }
+    }
+}
+
+/// A shot of the player, and how hard it hits.
+#[derive(Component)]
+pub struct PlayerShot {
+    pub damage: u8,
+    /// Enemies it still goes through.
+    pub pierce: u8,
+}
+
+impl PlayerShot {
+    /// Uses up one enemy of the pierce, true if the shot carries on.
+    pub fn pierce(&mut self) -> bool {
+        if self.pierce == 0 {
+            return false;
+        }
+        self.pierce -= 1;
+        true
+    }
+}
+
+/// Names the current weapon in the HUD.
+#[derive(Component)]
+pub struct WeaponDisplay;
+
+pub struct WeaponsPlugin;
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Weapons::load())
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(
+                        switch_weapon
+                            .label(GameStep::Input)
+                            .after(crate::handle_input),
+                    )
+                    .with_system(fire.label(GameStep::Input).after(switch_weapon)),
+            )
+            .add_system(show_weapon);
+    }
+}
+
+fn switch_weapon(
+    input: Res<PlayerInput>,
+    weapons: Res<Weapons>,
+    mut guns: Query<&mut Gun, With<Player>>,
+) {
+    let count = weapons.0.len();
+    for mut gun in guns.iter_mut() {
+        let index = match input.select_weapon {
+            Some(index) if index < count => index,
+            Some(_) => continue,
+            None if input.cycle_weapon != 0 => {
+                (gun.weapon as i32 + input.cycle_weapon).rem_euclid(count as i32) as usize
+            }
+            None => continue,
+        };
+        if index != gun.weapon {
+            *gun = Gun::new(index, &weapons.0[index]);
+        }
+    }
+}
+
+fn fire(
+    time: Res<Time>,
+    mut commands: Commands,
+    input: Res<PlayerInput>,
+    weapons: Res<Weapons>,
+    mut guns: Query<(&mut Gun, &Transform, Option<&PowerUps>), With<Player>>,
+    asset_server: Res<AssetServer>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (mut gun, transform, power_ups) in guns.iter_mut() {
+        let Some(weapon) = weapons.0.get(gun.weapon) else {
+            continue;
+        };
+        gun.cooldown -= time.delta_seconds() * power_ups.map_or(1.0, PowerUps::fire_rate);
+
+        let mut volleys = 0;
+        if gun.burst_left > 0 {
+            let finished = gun.burst.tick(time.delta()).times_finished_this_tick();
+            volleys = finished.min(gun.burst_left);
+            gun.burst_left -= volleys;
+        } else if input.fire && gun.cooldown <= 0.0 {
+            volleys = 1;
+            gun.burst_left = weapon.burst.saturating_sub(1);
+            gun.burst.reset();
+            // What's left over counts towards the next shot, but a slow
+            // frame doesn't save up shots for later.
+            gun.cooldown = (gun.cooldown + weapon.cooldown).max(0.0);
+        } else {
+            gun.cooldown = gun.cooldown.max(0.0);
+        }
+
+        for _ in 0..volleys {
+            fire_volley(&mut commands, weapon, transform, &asset_server, &mut rng);
+        }
+    }
+}
+
+/// One shot per pellet. A shot is a single entity that flies, hits, wraps
+/// and expires by itself; it needs no seam colliders of its own, as the
+/// enemies have theirs.
+fn fire_volley(
+    commands: &mut Commands,
+    weapon: &Weapon,
+    transform: &Transform,
+    asset_server: &AssetServer,
+    rng: &mut GameRng,
+) {
+    for pellet in 0..weapon.pellets {
+        let fanned = if weapon.pellets > 1 {
+            weapon.fan * (pellet as f32 / (weapon.pellets - 1) as f32 - 0.5)
+        } else {
+            0.0
+        };
+        let angle = fanned + rng.shots.gen_range(-weapon.spread..=weapon.spread);
+        let mut transform = *transform;
+        transform.rotate_z(angle);
+        transform.scale = weapon.scale.extend(1.0);
+
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(weapon.image.as_str()),
+                transform,
+                ..Default::default()
+            },
+            RigidBody::Dynamic,
+            Velocity {
+                angvel: 0.,
+                linvel: transform.local_y().truncate() * weapon.speed,
+            },
+            Collider::cuboid(1.0, 5.0),
+            // Hits are only reported, so a shot flies on through what it
+            // pierces and doesn't shove what it hits.
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            GravityScale(0.),
+            PlayerShot {
+                damage: weapon.damage,
+                pierce: weapon.pierce,
+            },
+            DropAfter {
+                time: weapon.lifetime,
+            },
+            WrapGhost,
+        ));
+    }
+}
+
+fn show_weapon(
+    weapons: Res<Weapons>,
+    guns: Query<&Gun, With<Player>>,
+    mut display: Query<&mut Text, With<WeaponDisplay>>,
+    mut shown: Local<Option<usize>>,
+) {
+    let Ok(gun) = guns.get_single() else {
+        return;
+    };
+    if *shown == Some(gun.weapon) {
+        return;
+    }
+    let Some(weapon) = weapons.0.get(gun.weapon) else {
+        return;
+    };
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Gun:   {}", weapon.name);
+        *shown = Some(gun.weapon);
+    }
+}
@@ -0,0 +1,356 @@
+use bevy::{
+    ecs::{entity::Entities, event::ManualEventReader},
+    prelude::*,
+    transform::TransformSystem,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{BoardSize, GameStep};
+
+/// Mirrors an entity's sprite across the torus. The entity keeps drawing
+/// itself; ghosts are children placed whole board sizes away, and only on the
+/// tiles where they can be seen by the camera.
+#[derive(Component)]
+pub struct WrapGhost;
+
+/// Marks anything that is only a picture of another entity, so it is left
+/// alone by the wrap-around logic.
+#[derive(Component)]
+pub struct SpriteCopy;
+
+/// Which board-sized tile, relative to its parent, a ghost sits on.
+#[derive(Component)]
+pub struct SpriteClone {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Gives an entity's collider copies on the eight neighbouring tiles, so it
+/// can be hit across the board edge. Contacts with a copy are reported again
+/// as [`CollisionEvent`]s of the entity itself, so readers should go through
+/// [`owner_collisions`] to skip the events of the [`SeamCollider`]s.
+#[derive(Component)]
+pub struct WrapCollider;
+
+/// One of the copies made for a [`WrapCollider`].
+#[derive(Component)]
+pub struct SeamCollider {
+    pub owner: Entity,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Furthest tile a ghost is put on, for when the board is smaller than the
+/// screen.
+const GHOST_REACH: i32 = 5;
+
+/// Assumed half extent of sprites without a `custom_size`.
+const DEFAULT_GHOST_RADIUS: f32 = 32.0;
+
+pub struct WrapGhostPlugin;
+
+impl Plugin for WrapGhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            spawn_seam_colliders
+                .label(GameStep::Wrap)
+                .after(crate::blink_system),
+        )
+        .add_system(
+            place_seam_colliders
+                .label(GameStep::Wrap)
+                .after(spawn_seam_colliders),
+        )
+        .add_system(
+            remove_ghosts
+                .label(GameStep::Wrap)
+                .after(place_seam_colliders),
+        )
+        .add_system(
+            sync_ghosts::<Sprite, Handle<Image>>
+                .label(GameStep::Wrap)
+                .after(remove_ghosts),
+        )
+        .add_system(
+            sync_ghosts::<TextureAtlasSprite, Handle<TextureAtlas>>
+                .label(GameStep::Wrap)
+                .after(remove_ghosts),
+        )
+        .add_system_to_stage(CoreStage::PreUpdate, forward_seam_collisions)
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            place_ghosts.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Local translation that puts a ghost of `parent` `offset` away from it in
+/// the world, undoing the parent's rotation and scale that the child
+/// inherits.
+fn ghost_translation(parent: &Transform, offset: Vec2) -> Vec3 {
+    let local = parent.rotation.inverse() * offset.extend(0.);
+    local / parent.scale
+}
+
+/// Tiles next to `position` whose copy of something `radius` big overlaps
+/// `view`.
+fn visible_tiles(position: Vec2, radius: f32, view: Rect, board_size: f32) -> Vec<(i32, i32)> {
+    let first = ((view.min - radius - position) / board_size).ceil();
+    let last = ((view.max + radius - position) / board_size).floor();
+
+    let mut tiles = vec![];
+    for x in (first.x as i32).max(-GHOST_REACH)..=(last.x as i32).min(GHOST_REACH) {
+        for y in (first.y as i32).max(-GHOST_REACH)..=(last.y as i32).min(GHOST_REACH) {
+            if (x, y) != (0, 0) {
+                tiles.push((x, y));
+            }
+        }
+    }
+    tiles
+}
+
+fn place_ghosts(
+    mut commands: Commands,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    originals: Query<
+        (
+            Entity,
+            &Transform,
+            AnyOf<(
+                (&Sprite, &Handle<Image>),
+                (&TextureAtlasSprite, &Handle<TextureAtlas>),
+            )>,
+            Option<&Children>,
+        ),
+        With<WrapGhost>,
+    >,
+    mut ghosts: Query<(&mut Transform, &mut SpriteClone), (Without<WrapGhost>, Without<Camera2d>)>,
+    board_size: Res<BoardSize>,
+) {
+    let Ok((camera_transform, projection)) = camera.get_single() else {
+        return;
+    };
+    let size = board_size.size.abs();
+    let view = Rect {
+        min: camera_transform.translation.truncate()
+            + Vec2::new(projection.left, projection.bottom) * projection.scale,
+        max: camera_transform.translation.truncate()
+            + Vec2::new(projection.right, projection.top) * projection.scale,
+    };
+
+    for (original, transform, (plain, sheet), children) in originals.iter() {
+        let custom_size = match (plain, sheet) {
+            (Some((sprite, _)), _) => sprite.custom_size,
+            (_, Some((sprite, _))) => sprite.custom_size,
+            (None, None) => None,
+        };
+        let radius = custom_size.map_or(DEFAULT_GHOST_RADIUS, |custom_size| {
+            custom_size.max_element() / 2.0
+        }) * transform.scale.truncate().abs().max_element();
+        let mut tiles = if size < 1.0 {
+            vec![]
+        } else {
+            visible_tiles(transform.translation.truncate(), radius, view, size)
+        }
+        .into_iter();
+
+        for &child in children.into_iter().flatten() {
+            let Ok((mut ghost_transform, mut location)) = ghosts.get_mut(child) else {
+                continue;
+            };
+            match tiles.next() {
+                Some((x, y)) => {
+                    *location = SpriteClone { x, y };
+                    ghost_transform.translation =
+                        ghost_translation(transform, Vec2::new(x as f32, y as f32) * size);
+                }
+                None => commands.entity(child).despawn_recursive(),
+            }
+        }
+
+        let new_ghosts: Vec<Entity> = tiles
+            .map(|(x, y)| {
+                let mut ghost = commands.spawn((
+                    SpatialBundle::from_transform(Transform::from_translation(ghost_translation(
+                        transform,
+                        Vec2::new(x as f32, y as f32) * size,
+                    ))),
+                    SpriteCopy,
+                    SpriteClone { x, y },
+                ));
+                if let Some((sprite, texture)) = plain {
+                    ghost.insert((sprite.clone(), texture.clone()));
+                }
+                if let Some((sprite, texture_atlas)) = sheet {
+                    ghost.insert((sprite.clone(), texture_atlas.clone()));
+                }
+                ghost.id()
+            })
+            .collect();
+        if !new_ghosts.is_empty() {
+            commands.entity(original).push_children(&new_ghosts);
+        }
+    }
+}
+
+/// Copies the look of originals drawn with a `S` from a `T` onto their
+/// ghosts.
+fn sync_ghosts<S: Component + Clone, T: Component + Clone>(
+    originals: Query<(&S, &T, &Children), (With<WrapGhost>, Or<(Changed<S>, Changed<T>)>)>,
+    mut ghosts: Query<(&mut S, &mut T), (With<SpriteClone>, Without<WrapGhost>)>,
+) {
+    for (sprite, texture, children) in originals.iter() {
+        for &child in children.iter() {
+            if let Ok((mut ghost_sprite, mut ghost_texture)) = ghosts.get_mut(child) {
+                *ghost_sprite = sprite.clone();
+                *ghost_texture = texture.clone();
+            }
+        }
+    }
+}
+
+fn remove_ghosts(
+    mut commands: Commands,
+    removed: RemovedComponents<WrapGhost>,
+    originals: Query<&Children>,
+    ghosts: Query<Entity, With<SpriteClone>>,
+) {
+    for original in removed.iter() {
+        if let Ok(children) = originals.get(original) {
+            for ghost in ghosts.iter_many(children) {
+                commands.entity(ghost).despawn_recursive();
+            }
+        }
+    }
+}
+
+pub(crate) fn spawn_seam_colliders(
+    mut commands: Commands,
+    originals: Query<(Entity, &Transform, &Collider), Added<WrapCollider>>,
+    board_size: Res<BoardSize>,
+) {
+    for (owner, transform, collider) in originals.iter() {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                if (x, y) == (0, 0) {
+                    continue;
+                }
+                commands.spawn((
+                    collider.clone(),
+                    Sensor,
+                    ActiveEvents::COLLISION_EVENTS,
+                    TransformBundle::from_transform(Transform::from_translation(
+                        transform.translation + Vec3::new(x as f32, y as f32, 0.) * board_size.size,
+                    )),
+                    SeamCollider { owner, x, y },
+                    SpriteCopy,
+                ));
+            }
+        }
+    }
+}
+
+fn place_seam_colliders(
+    mut commands: Commands,
+    mut seams: Query<(Entity, &SeamCollider, &mut Transform, &mut Collider)>,
+    originals: Query<
+        (&Transform, &Collider, ChangeTrackers<Collider>),
+        (With<WrapCollider>, Without<SeamCollider>),
+    >,
+    board_size: Res<BoardSize>,
+) {
+    for (seam, location, mut transform, mut collider) in seams.iter_mut() {
+        let Ok((owner_transform, owner_collider, collider_tracker)) = originals.get(location.owner)
+        else {
+            commands.entity(seam).despawn_recursive();
+            continue;
+        };
+        transform.translation = owner_transform.translation
+            + Vec3::new(location.x as f32, location.y as f32, 0.) * board_size.size;
+        if collider_tracker.is_changed() {
+            *collider = owner_collider.clone();
+        }
+    }
+}
+
+fn forward_seam_collisions(
+    mut collisions: ResMut<Events<CollisionEvent>>,
+    mut reader: Local<ManualEventReader<CollisionEvent>>,
+    seams: Query<&SeamCollider>,
+    entities: &Entities,
+) {
+    let owner = |entity: Entity| seams.get(entity).map_or(entity, |seam| seam.owner);
+    let forwarded: Vec<CollisionEvent> = reader
+        .iter(&collisions)
+        .filter_map(|collision| match *collision {
+            CollisionEvent::Started(a, b, flags) if seams.contains(a) || seams.contains(b) => {
+                Some(CollisionEvent::Started(owner(a), owner(b), flags))
+            }
+            CollisionEvent::Stopped(a, b, flags) if seams.contains(a) || seams.contains(b) => {
+                Some(CollisionEvent::Stopped(owner(a), owner(b), flags))
+            }
+            _ => None,
+        })
+        // A copy touching its own owner, when they spawn together, or one
+        // left over for a frame after its owner was despawned.
+        .filter(|collision| match *collision {
+            CollisionEvent::Started(a, b, _) | CollisionEvent::Stopped(a, b, _) => {
+                a != b && entities.contains(a) && entities.contains(b)
+            }
+        })
+        .collect();
+    collisions.extend(forwarded);
+}
+
+/// The pairs of entities that started touching, leaving out the events of
+/// [`SeamCollider`]s, which are also reported for their owners.
+pub fn owner_collisions<'a>(
+    collisions: &'a mut EventReader<CollisionEvent>,
+    seams: &'a Query<(), With<SeamCollider>>,
+) -> impl Iterator<Item = (Entity, Entity)> + use<'a> {
+    collisions.iter().filter_map(|collision| match *collision {
+        CollisionEvent::Started(a, b, _) if !seams.contains(a) && !seams.contains(b) => {
+            Some((a, b))
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_3;
+
+    use super::*;
+
+    fn lands_on(parent: Transform, offset: Vec2) {
+        // What transform propagation makes of the child.
+        let ghost = GlobalTransform::from(parent)
+            .mul_transform(Transform::from_translation(ghost_translation(
+                &parent, offset,
+            )))
+            .translation();
+        let expected = parent.translation + offset.extend(0.);
+        assert!((ghost - expected).length() < 1e-3, "{ghost} != {expected}");
+    }
+
+    #[test]
+    fn ghost_of_plain_parent_is_a_board_away() {
+        lands_on(Transform::from_xyz(100., -50., 0.), Vec2::new(800., 0.));
+    }
+
+    #[test]
+    fn ghost_ignores_parent_rotation() {
+        let parent =
+            Transform::from_xyz(100., -50., 0.).with_rotation(Quat::from_rotation_z(FRAC_PI_3));
+        lands_on(parent, Vec2::new(800., -800.));
+    }
+
+    #[test]
+    fn ghost_ignores_parent_scale() {
+        let parent = Transform::from_xyz(-300., 20., 0.)
+            .with_rotation(Quat::from_rotation_z(-1.2))
+            .with_scale(Vec3::new(1., 4., 1.));
+        lands_on(parent, Vec2::new(0., 800.));
+        lands_on(parent, Vec2::new(-1600., 800.));
+    }
+}
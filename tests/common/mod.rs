@@ -0,0 +1,63 @@
+//! Scripts and checks shared by the headless tests.
+
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_astro::{archetypes::Enemy, input::PlayerInput, Player, Score};
+
+/// A frame at 60 frames per second.
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+pub fn restart() -> PlayerInput {
+    PlayerInput {
+        restart: true,
+        ..default()
+    }
+}
+
+/// Flies around, switching weapons, firing and dashing.
+pub fn play(frames: usize) -> Vec<PlayerInput> {
+    (0..frames)
+        .map(|frame| PlayerInput {
+            aim: Some(frame as f32 * 0.05),
+            thrust: frame % 90 < 40,
+            fire: frame % 30 < 20,
+            select_weapon: (frame % 200 == 0).then_some(frame / 200 % 5),
+            dash: frame % 150 == 75,
+            ..default()
+        })
+        .collect()
+}
+
+/// Starts a run and plays, pressing restart now and then, so a run that
+/// went down starts over.
+pub fn session(frames: usize) -> Vec<PlayerInput> {
+    let mut script = vec![restart()];
+    script.extend(
+        play(frames)
+            .into_iter()
+            .enumerate()
+            .map(|(frame, input)| PlayerInput {
+                restart: frame % 500 == 499,
+                ..input
+            }),
+    );
+    script
+}
+
+/// Where the player and every enemy are, and the score.
+pub type Snapshot = (Vec3, Vec<(String, Vec3)>, f32);
+
+pub fn snapshot(app: &mut App) -> Snapshot {
+    let mut player = app.world.query_filtered::<&Transform, With<Player>>();
+    let player = player.single(&app.world).translation;
+    let mut enemies = app.world.query::<(&Enemy, &Transform)>();
+    let mut enemies: Vec<_> = enemies
+        .iter(&app.world)
+        .map(|(enemy, transform)| (enemy.archetype.clone(), transform.translation))
+        .collect();
+    enemies.sort_by(|a, b| format!("{a:?}").cmp(&format!("{b:?}")));
+    (player, enemies, app.world.resource::<Score>().score)
+}
@@ -0,0 +1,23 @@
+//! The whole game runs without a window for a long scripted session.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_astro::{headless_app, input::InputScript, rng::GameRng, GameState, TimeCounter};
+use common::{session, FRAME};
+
+#[test]
+fn scripted_session_runs_for_thousands_of_frames() {
+    const FRAMES: usize = 3000;
+    let mut app = headless_app(InputScript::new(session(FRAMES)), FRAME);
+    app.insert_resource(GameRng::new(3));
+    let mut running = 0;
+    for _ in 0..=FRAMES {
+        app.update();
+        if app.world.resource::<State<GameState>>().current() == &GameState::Running {
+            running += 1;
+        }
+    }
+    assert!(running > FRAMES / 2, "only {running} frames were played");
+    assert!(app.world.resource::<TimeCounter>().score > 0.0);
+}
@@ -0,0 +1,75 @@
+//! A recorded session, saved and loaded again, plays back the same way.
+
+mod common;
+
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_astro::{
+    headless_app,
+    hull::HullConfig,
+    input::InputScript,
+    replay::{headless_replay, RecordReplayPlugin, Replay},
+    rng::GameRng,
+    GameState,
+};
+use common::{session, snapshot, FRAME};
+
+const FRAMES: usize = 3000;
+
+/// Frames between the snapshots that are compared.
+const EVERY: usize = 100;
+
+#[test]
+fn saved_replay_plays_back_the_same() {
+    for seed in [1, 2, 3, 42] {
+        record_and_play_back(seed);
+    }
+}
+
+fn record_and_play_back(seed: u64) {
+    let path = std::env::temp_dir().join(format!("bevy-astro-replay-{}.ron", std::process::id()));
+
+    let mut app = headless_app(InputScript::new(session(FRAMES)), FRAME);
+    // Each run draws its own seed from the session's.
+    app.insert_resource(GameRng::session(seed, false))
+        .add_plugin(RecordReplayPlugin { path: path.clone() });
+    let recorded = run(&mut app);
+    let seeds: Vec<u64> = recorded.iter().filter_map(|(_, seed)| *seed).collect();
+    assert!(
+        seeds.windows(2).any(|pair| pair[0] != pair[1]),
+        "session {seed}: the player never started over"
+    );
+
+    app.world.resource::<Replay>().save(&path).unwrap();
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.frames.len(), FRAMES + 1);
+
+    let mut app = headless_replay(replay);
+    assert!(
+        run(&mut app) == recorded,
+        "session {seed}: the replay played out differently"
+    );
+}
+
+/// Snapshots of the session now and then, with the run's seed while one is
+/// going.
+fn run(app: &mut App) -> Vec<(common::Snapshot, Option<u64>)> {
+    // One hit ends the run, so the session gets through a few of them.
+    let mut config = app.world.resource_mut::<HullConfig>();
+    config.hull = 1.0;
+    config.shield = 0.0;
+    config.lives = None;
+
+    let mut snapshots = vec![];
+    for frame in 0..=FRAMES {
+        app.update();
+        if frame % EVERY == 0 {
+            let running = app.world.resource::<State<GameState>>().current() == &GameState::Running;
+            let seed = running.then(|| app.world.resource::<GameRng>().seed());
+            snapshots.push((snapshot(app), seed));
+        }
+    }
+    snapshots
+}
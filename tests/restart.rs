@@ -0,0 +1,38 @@
+//! A run started over with the same seed plays out the same, however the
+//! run before it ended.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_astro::{headless_app, input::InputScript, rng::GameRng, GameState};
+use common::{play, restart, snapshot, FRAME};
+
+#[test]
+fn restarted_run_plays_out_the_same() {
+    const FRAMES: usize = 900;
+    // The very first update takes no time, so spend it on the title screen
+    // and let both runs start on a full frame.
+    let mut script = vec![default(), restart()];
+    script.extend(play(FRAMES));
+    // The test ends the run here, then a frame on the end screen.
+    script.push(default());
+    script.push(restart());
+    script.extend(play(FRAMES));
+
+    let mut app = headless_app(InputScript::new(script), FRAME);
+    app.insert_resource(GameRng::new(7));
+    for _ in 0..FRAMES + 2 {
+        app.update();
+    }
+    let first = snapshot(&mut app);
+    assert!(!first.1.is_empty(), "no enemies showed up");
+
+    let _ = app
+        .world
+        .resource_mut::<State<GameState>>()
+        .overwrite_set(GameState::Died);
+    for _ in 0..FRAMES + 2 {
+        app.update();
+    }
+    assert_eq!(snapshot(&mut app), first);
+}
@@ -0,0 +1,118 @@
+//! Pictures that are rotated or scaled still have their ghosts drawn whole
+//! boards away, where they show across the seam.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_astro::{
+    dash::Afterimage,
+    headless_app,
+    input::{InputScript, PlayerInput},
+    weapons::PlayerShot,
+    wrap_ghost::SpriteClone,
+    BoardSize, Player,
+};
+use common::{restart, FRAME};
+
+/// Starts a run with the player at `position` and a camera that sees the
+/// whole board, as the window would.
+fn start(frames: Vec<PlayerInput>, position: Vec3) -> App {
+    let mut script = vec![restart()];
+    script.extend(frames);
+    let mut app = headless_app(InputScript::new(script), FRAME);
+    app.update();
+    let mut projections = app.world.query::<&mut OrthographicProjection>();
+    for mut projection in projections.iter_mut(&mut app.world) {
+        projection.left = -400.;
+        projection.right = 400.;
+        projection.bottom = -300.;
+        projection.top = 300.;
+    }
+    let mut player = app.world.query_filtered::<&mut Transform, With<Player>>();
+    player.single_mut(&mut app.world).translation = position;
+    app
+}
+
+/// Checks every ghost of an `F` against where its original is, returning
+/// how many there were.
+fn check_ghosts<F: bevy::ecs::query::ReadOnlyWorldQuery>(app: &mut App) -> usize {
+    let size = app.world.resource::<BoardSize>().size;
+    let mut originals = app
+        .world
+        .query_filtered::<(&GlobalTransform, &Children), F>();
+    let mut ghosts = app.world.query::<(&GlobalTransform, &SpriteClone)>();
+    let mut checked = 0;
+    for (original, children) in originals.iter(&app.world) {
+        for &child in children.iter() {
+            let Ok((ghost, tile)) = ghosts.get(&app.world, child) else {
+                continue;
+            };
+            // Spawned this frame, not placed until the next.
+            if ghost.translation() == Vec3::ZERO {
+                continue;
+            }
+            let expected =
+                original.translation() + Vec3::new(tile.x as f32, tile.y as f32, 0.) * size;
+            assert!(
+                (ghost.translation() - expected).length() < 0.01,
+                "ghost on {},{} at {} instead of {}",
+                tile.x,
+                tile.y,
+                ghost.translation(),
+                expected
+            );
+            checked += 1;
+        }
+    }
+    checked
+}
+
+#[test]
+fn dash_trail_shows_across_the_seam() {
+    let aim = Some(0.7);
+    let mut frames = vec![PlayerInput { aim, ..default() }; 5];
+    frames.push(PlayerInput {
+        aim,
+        dash: true,
+        thrust_direction: Some(Vec2::X),
+        ..default()
+    });
+    frames.extend(vec![PlayerInput { aim, ..default() }; 20]);
+    let mut app = start(frames, Vec3::new(370., 0., 0.));
+
+    let mut checked = 0;
+    for _ in 0..26 {
+        app.update();
+        checked += check_ghosts::<With<Afterimage>>(&mut app);
+    }
+    assert!(checked > 0, "no afterimage was ghosted");
+}
+
+#[test]
+fn stretched_shots_show_across_the_seam() {
+    // The laser and the cannon, whose shots are scaled.
+    for weapon in [2, 3] {
+        let aim = Some(0.3);
+        let mut frames = vec![PlayerInput {
+            aim,
+            select_weapon: Some(weapon),
+            ..default()
+        }];
+        frames.extend(vec![
+            PlayerInput {
+                aim,
+                fire: true,
+                ..default()
+            };
+            60
+        ]);
+        let mut app = start(frames, Vec3::new(300., 0., 0.));
+
+        let mut checked = 0;
+        for _ in 0..61 {
+            app.update();
+            checked += check_ghosts::<With<PlayerShot>>(&mut app);
+        }
+        assert!(checked > 0, "no shot of weapon {weapon} was ghosted");
+    }
+}